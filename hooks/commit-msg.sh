@@ -0,0 +1,25 @@
+#!/bin/sh
+#
+# Git hook to validate commit messages using i-am-committed
+#
+# This hook is called by 'git commit' with one argument:
+# 1. The name of the file that contains the commit log message.
+#
+# A non-zero exit status aborts the commit. Rule overrides such as
+# --types, --scopes or --require-scope can be added to the command below.
+
+# The iamcommitted binary is looked up on the PATH. Set IAMCOMMITTED to use
+# a binary somewhere else.
+EXECUTABLE_PATH="${IAMCOMMITTED:-$(command -v iamcommitted)}"
+
+# Check if the executable exists and is executable
+if [ -z "$EXECUTABLE_PATH" ] || [ ! -x "$EXECUTABLE_PATH" ]; then
+  echo "Error: iamcommitted executable not found. Install it to a directory on your PATH"
+  echo "(e.g. with 'cargo install --path .') or set IAMCOMMITTED to its location."
+  exit 1
+fi
+
+"$EXECUTABLE_PATH" commit-msg "$1"
+
+# Exit with the status of the iamcommitted binary
+exit $?
@@ -0,0 +1,558 @@
+/// Tokens of diff sent to the model when no `max_diff_tokens` is configured, leaving
+/// room for the prompt and the answer in small context windows
+pub const DEFAULT_MAX_DIFF_TOKENS: usize = 12_000;
+
+/// Unchanged lines kept on either side of a change when context is trimmed
+const TRIMMED_CONTEXT_LINES: usize = 1;
+
+/// Heading of the diffstat listing what was cut
+const SHORTENED_HEADING: &str = "\nChanges not shown in full (file | lines added, removed):\n";
+
+/// Marks a file reduced to its hunk headers in the diffstat
+const REDUCED_NOTE: &str = " (hunk headers only)";
+
+/// Lock files, whose diffs say nothing about the intent of a change
+const LOCK_FILES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+    "poetry.lock",
+    "Pipfile.lock",
+    "uv.lock",
+    "composer.lock",
+    "Gemfile.lock",
+    "go.sum",
+    "flake.lock",
+    "mix.lock",
+    "packages.lock.json",
+];
+
+/// Suffixes of minified, generated and snapshot files
+const GENERATED_SUFFIXES: &[&str] = &[
+    ".min.js",
+    ".min.css",
+    ".map",
+    ".snap",
+    ".pb.go",
+    "_pb2.py",
+    ".g.dart",
+    ".designer.cs",
+];
+
+/// A diff cut down to the token budget
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FittedDiff {
+    pub diff: String,
+    /// Estimated tokens before and after fitting
+    pub original_tokens: usize,
+    pub tokens: usize,
+    /// What was cut, e.g. `dropped Cargo.lock`; empty when the diff fit as it was
+    pub actions: Vec<String>,
+}
+
+/// A diff split into parts small enough to be summarised one at a time
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffParts {
+    pub parts: Vec<String>,
+    /// Lock and generated files, which are not worth summarising
+    pub skipped: Vec<String>,
+}
+
+/// Rough number of tokens the model's tokenizer turns the text into
+pub fn estimate_tokens(text: &str, model: &str) -> usize {
+    (text.chars().count() as f64 / chars_per_token(model)).ceil() as usize
+}
+
+/// Average characters per token of code and diffs for the model's tokenizer family
+fn chars_per_token(model: &str) -> f64 {
+    // Routers name models `vendor/model`
+    let model = model.rsplit('/').next().unwrap_or(model).to_lowercase();
+    let starts_with = |prefixes: &[&str]| prefixes.iter().any(|prefix| model.starts_with(prefix));
+
+    if starts_with(&["gpt-4o", "gpt-4.1", "gpt-5", "o1", "o3", "o4", "chatgpt"]) {
+        4.0
+    } else if starts_with(&["gpt-4", "gpt-3.5"]) {
+        3.7
+    } else if starts_with(&["claude", "gemini"]) {
+        3.5
+    } else {
+        // Llama, Qwen, Mistral and other open models split code into more tokens
+        3.2
+    }
+}
+
+/// Shrink a diff to about `max_tokens` tokens, 0 meaning no limit. In order, until it
+/// fits: lock and generated files are dropped, context lines are trimmed, the largest
+/// files are reduced to their hunk headers, and finally left out. Everything dropped
+/// or reduced is listed in a diffstat below the diff.
+pub fn fit_diff(diff: &str, model: &str, max_tokens: usize) -> FittedDiff {
+    let original_tokens = estimate_tokens(diff, model);
+    if max_tokens == 0 || original_tokens <= max_tokens {
+        return FittedDiff {
+            diff: diff.to_string(),
+            original_tokens,
+            tokens: original_tokens,
+            actions: Vec::new(),
+        };
+    }
+
+    let budget = (max_tokens as f64 * chars_per_token(model)) as usize;
+    let file_budget = budget.saturating_sub(SHORTENED_HEADING.len());
+    let mut files = parse_diff(diff);
+    let mut actions = Vec::new();
+    let size = |files: &[FileDiff]| files.iter().map(FileDiff::chars).sum::<usize>();
+
+    let generated: Vec<String> = files
+        .iter_mut()
+        .filter(|file| is_generated(&file.path))
+        .map(|file| {
+            file.omitted = true;
+            file.path.clone()
+        })
+        .collect();
+    if !generated.is_empty() {
+        actions.push(format!("dropped {}", generated.join(", ")));
+    }
+
+    if size(&files) > file_budget {
+        for file in files.iter_mut() {
+            file.trim_context(TRIMMED_CONTEXT_LINES);
+        }
+        actions.push(format!(
+            "trimmed context to {} line(s) around each change",
+            TRIMMED_CONTEXT_LINES
+        ));
+    }
+
+    // Largest files first; sizes are tracked incrementally as files can be many
+    let mut order: Vec<usize> = (0..files.len()).filter(|&i| !files[i].omitted).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(files[i].chars()));
+    let mut total = size(&files);
+
+    let mut summarised = Vec::new();
+    for &index in &order {
+        if total <= file_budget {
+            break;
+        }
+        let file = &mut files[index];
+        let before = file.chars();
+        file.reduced = true;
+        let after = file.chars();
+        // Small hunks take less room than their diffstat line
+        if after >= before {
+            file.reduced = false;
+            continue;
+        }
+        total = total - before + after;
+        summarised.push(file.path.clone());
+    }
+    if !summarised.is_empty() {
+        actions.push(format!("reduced {} to hunk headers", summarised.join(", ")));
+    }
+
+    let mut omitted = Vec::new();
+    for &index in &order {
+        if total <= file_budget {
+            break;
+        }
+        let file = &mut files[index];
+        let before = file.chars();
+        file.omitted = true;
+        total = total - before + file.chars();
+        omitted.push(file.path.clone());
+    }
+    if !omitted.is_empty() {
+        actions.push(format!("left out {}", omitted.join(", ")));
+    }
+
+    let mut fitted = render(&files);
+    if fitted.chars().count() > budget {
+        // Only a diffstat of thousands of files gets here
+        fitted = fitted.chars().take(budget).collect();
+        fitted.push_str("\n[truncated]\n");
+        actions.push("cut the file list short".to_string());
+    }
+
+    FittedDiff {
+        tokens: estimate_tokens(&fitted, model),
+        diff: fitted,
+        original_tokens,
+        actions,
+    }
+}
+
+/// Split a diff into parts of about `max_tokens` tokens. Small files are grouped,
+/// large ones are split between hunks, and a hunk too large on its own between lines.
+pub fn split_diff(diff: &str, model: &str, max_tokens: usize) -> DiffParts {
+    let budget = ((max_tokens as f64 * chars_per_token(model)) as usize).max(1);
+    let mut split = DiffParts::default();
+    let mut current = String::new();
+    let mut current_len = 0;
+    for file in parse_diff(diff) {
+        if is_generated(&file.path) {
+            split.skipped.push(file.path);
+            continue;
+        }
+        for piece in file.pieces(budget) {
+            let piece_len = piece.chars().count();
+            if current_len > 0 && current_len + piece_len > budget {
+                split.parts.push(std::mem::take(&mut current));
+                current_len = 0;
+            }
+            current.push_str(&piece);
+            current_len += piece_len;
+        }
+    }
+    if !current.is_empty() {
+        split.parts.push(current);
+    }
+    split
+}
+
+fn is_generated(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    LOCK_FILES.contains(&name)
+        || GENERATED_SUFFIXES
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+}
+
+/// One file of a `git diff`
+#[derive(Debug, Clone)]
+struct FileDiff {
+    path: String,
+    /// `diff --git`, `index`, `---` and `+++` lines, or the binary file notice
+    header: Vec<String>,
+    hunks: Vec<Hunk>,
+    added: usize,
+    removed: usize,
+    /// Hunks were reduced to their headers
+    reduced: bool,
+    /// Left out of the diff entirely
+    omitted: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Hunk {
+    /// The `@@ -1,3 +1,4 @@` line
+    header: String,
+    lines: Vec<String>,
+}
+
+impl Hunk {
+    /// The hunk in parts of at most `room` characters, split between lines and each
+    /// starting with the hunk header. A single line longer than `room` gets a part of its own.
+    fn parts(&self, room: usize) -> Vec<String> {
+        let header = format!("{}\n", self.header);
+        let header_len = header.chars().count();
+
+        let mut parts = Vec::new();
+        let mut part = header.clone();
+        let mut part_len = header_len;
+        for line in &self.lines {
+            let line_len = line.chars().count() + 1;
+            if part_len > header_len && part_len + line_len > room {
+                parts.push(std::mem::replace(&mut part, header.clone()));
+                part_len = header_len;
+            }
+            part.push_str(line);
+            part.push('\n');
+            part_len += line_len;
+        }
+        parts.push(part);
+        parts
+    }
+}
+
+impl FileDiff {
+    /// Length of the file's part of the rendered diff, including its diffstat line
+    fn chars(&self) -> usize {
+        let stat = self.stat().chars().count() + 1;
+        if self.omitted {
+            return stat;
+        }
+        let lines = self.header.iter().chain(self.hunks.iter().flat_map(|hunk| {
+            std::iter::once(&hunk.header).chain(if self.reduced {
+                [].iter()
+            } else {
+                hunk.lines.iter()
+            })
+        }));
+        let diff: usize = lines.map(|line| line.chars().count() + 1).sum();
+        if self.reduced {
+            diff + stat + REDUCED_NOTE.len()
+        } else {
+            diff
+        }
+    }
+
+    /// The file's diff in pieces of about `budget` characters, each starting with
+    /// the file header so it can be read on its own
+    fn pieces(&self, budget: usize) -> Vec<String> {
+        let header: String = self
+            .header
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect();
+        let header_len = header.chars().count();
+        let room = budget.saturating_sub(header_len);
+
+        let mut pieces = Vec::new();
+        let mut piece = header.clone();
+        let mut piece_len = header_len;
+        for text in self.hunks.iter().flat_map(|hunk| hunk.parts(room)) {
+            let text_len = text.chars().count();
+            if piece_len > header_len && piece_len + text_len > budget {
+                pieces.push(std::mem::replace(&mut piece, header.clone()));
+                piece_len = header_len;
+            }
+            piece.push_str(&text);
+            piece_len += text_len;
+        }
+        pieces.push(piece);
+        pieces
+    }
+
+    fn stat(&self) -> String {
+        format!("{} | +{} -{}", self.path, self.added, self.removed)
+    }
+
+    /// Drop unchanged lines further than `keep` lines from a change
+    fn trim_context(&mut self, keep: usize) {
+        for hunk in &mut self.hunks {
+            let changed: Vec<usize> = hunk
+                .lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| !line.starts_with(' ') && !line.is_empty())
+                .map(|(index, _)| index)
+                .collect();
+            let lines = std::mem::take(&mut hunk.lines);
+            hunk.lines = lines
+                .into_iter()
+                .enumerate()
+                .filter(|(index, _)| {
+                    changed
+                        .iter()
+                        .any(|&change| change.abs_diff(*index) <= keep)
+                })
+                .map(|(_, line)| line)
+                .collect();
+        }
+    }
+}
+
+fn parse_diff(diff: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    for line in diff.lines() {
+        if let Some(paths) = line.strip_prefix("diff --git ") {
+            let path = paths
+                .rsplit_once(" b/")
+                .map(|(_, path)| path)
+                .unwrap_or(paths);
+            files.push(FileDiff {
+                path: path.to_string(),
+                header: vec![line.to_string()],
+                hunks: Vec::new(),
+                added: 0,
+                removed: 0,
+                reduced: false,
+                omitted: false,
+            });
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+        if line.starts_with("@@") {
+            file.hunks.push(Hunk {
+                header: line.to_string(),
+                lines: Vec::new(),
+            });
+        } else if let Some(hunk) = file.hunks.last_mut() {
+            if line.starts_with('+') {
+                file.added += 1;
+            } else if line.starts_with('-') {
+                file.removed += 1;
+            }
+            hunk.lines.push(line.to_string());
+        } else {
+            file.header.push(line.to_string());
+        }
+    }
+    files
+}
+
+fn render(files: &[FileDiff]) -> String {
+    let mut output = String::new();
+    for file in files.iter().filter(|file| !file.omitted) {
+        for line in &file.header {
+            output.push_str(line);
+            output.push('\n');
+        }
+        for hunk in &file.hunks {
+            output.push_str(&hunk.header);
+            output.push('\n');
+            if !file.reduced {
+                for line in &hunk.lines {
+                    output.push_str(line);
+                    output.push('\n');
+                }
+            }
+        }
+    }
+
+    let shortened: Vec<&FileDiff> = files
+        .iter()
+        .filter(|file| file.omitted || file.reduced)
+        .collect();
+    if !shortened.is_empty() {
+        output.push_str(SHORTENED_HEADING);
+        for file in shortened {
+            output.push_str(&file.stat());
+            if file.reduced && !file.omitted {
+                output.push_str(REDUCED_NOTE);
+            }
+            output.push('\n');
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_diff(path: &str, hunk_lines: &[&str]) -> String {
+        let mut diff = format!(
+            "diff --git a/{path} b/{path}\nindex 123..456 100644\n--- a/{path}\n+++ b/{path}\n@@ -1,{n} +1,{n} @@ fn main()\n",
+            path = path,
+            n = hunk_lines.len()
+        );
+        for line in hunk_lines {
+            diff.push_str(line);
+            diff.push('\n');
+        }
+        diff
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        let text = "x".repeat(400);
+        assert_eq!(estimate_tokens(&text, "gpt-4o-mini"), 100);
+        assert_eq!(estimate_tokens(&text, "openai/gpt-4o"), 100);
+        assert!(estimate_tokens(&text, "llama3.2") > 100);
+    }
+
+    #[test]
+    fn test_small_diff_is_unchanged() {
+        let diff = file_diff("src/main.rs", &["+fn main() {}"]);
+        let fitted = fit_diff(&diff, "gpt-4o", 1000);
+        assert_eq!(fitted.diff, diff);
+        assert!(fitted.actions.is_empty());
+        assert_eq!(fit_diff(&diff, "gpt-4o", 0).diff, diff);
+    }
+
+    #[test]
+    fn test_lock_files_are_dropped_first() {
+        let lock: Vec<String> = (0..200).map(|i| format!("+checksum = \"{}\"", i)).collect();
+        let lock: Vec<&str> = lock.iter().map(String::as_str).collect();
+        let diff = file_diff("src/main.rs", &["+fn main() {}"]) + &file_diff("Cargo.lock", &lock);
+
+        let fitted = fit_diff(&diff, "gpt-4o", 200);
+        assert!(fitted.diff.contains("+fn main() {}"));
+        assert!(!fitted.diff.contains("checksum"));
+        assert!(fitted.diff.contains("Cargo.lock | +200 -0\n"));
+        assert_eq!(fitted.actions, ["dropped Cargo.lock"]);
+        assert!(fitted.tokens <= 200);
+    }
+
+    #[test]
+    fn test_context_is_trimmed_then_files_reduced() {
+        let mut lines: Vec<String> = (0..40).map(|i| format!(" context line {}", i)).collect();
+        lines.insert(20, "+new line".to_string());
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let diff = file_diff("src/lib.rs", &lines);
+
+        let fitted = fit_diff(&diff, "gpt-4o", 60);
+        assert!(fitted
+            .diff
+            .contains(" context line 19\n+new line\n context line 20\n"));
+        assert!(!fitted.diff.contains("context line 18"));
+        assert_eq!(fitted.actions.len(), 1);
+
+        let big: Vec<String> = (0..300).map(|i| format!("+generated {}", i)).collect();
+        let big: Vec<&str> = big.iter().map(String::as_str).collect();
+        let diff = diff + &file_diff("src/big.rs", &big);
+        let fitted = fit_diff(&diff, "gpt-4o", 120);
+        assert!(fitted.diff.contains("+new line"));
+        assert!(fitted.diff.contains("@@ -1,300 +1,300 @@ fn main()\n"));
+        assert!(!fitted.diff.contains("+generated"));
+        assert!(fitted
+            .diff
+            .contains("src/big.rs | +300 -0 (hunk headers only)\n"));
+        assert_eq!(fitted.actions[1], "reduced src/big.rs to hunk headers");
+        assert!(fitted.tokens <= 120, "{}", fitted.tokens);
+    }
+
+    #[test]
+    fn test_split_diff() {
+        let small = file_diff("src/a.rs", &["+a"]) + &file_diff("src/b.rs", &["+b"]);
+        let split = split_diff(
+            &(small.clone() + &file_diff("go.sum", &["+x"])),
+            "gpt-4o",
+            100,
+        );
+        assert_eq!(split.parts, [small]);
+        assert_eq!(split.skipped, ["go.sum"]);
+
+        let mut big = file_diff("src/big.rs", &["+one", "+two"]);
+        big.push_str("@@ -10,2 +10,2 @@\n+three\n+four\n");
+        let lines: Vec<String> = (0..500).map(|i| format!("+line {}", i)).collect();
+        big.push_str("@@ -20,500 +20,500 @@\n");
+        big.push_str(&lines.join("\n"));
+        let split = split_diff(&big, "gpt-4o", 100);
+        assert!(split.parts[0].contains("+two\n@@ -10,2 +10,2 @@\n+three"));
+        assert!(split.parts[1].starts_with("diff --git a/src/big.rs b/src/big.rs\n"));
+        assert!(split
+            .parts
+            .iter()
+            .all(|part| estimate_tokens(part, "gpt-4o") <= 100));
+    }
+
+    #[test]
+    fn test_split_diff_splits_large_hunks() {
+        let lines: Vec<String> = (0..300).map(|i| format!("+line {}", i)).collect();
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let split = split_diff(&file_diff("src/big.rs", &lines), "gpt-4o", 100);
+
+        assert!(split.parts.len() > 2, "{}", split.parts.len());
+        for part in &split.parts {
+            assert!(part.starts_with("diff --git a/src/big.rs b/src/big.rs\n"));
+            assert!(part.contains("\n@@ -1,300 +1,300 @@"));
+            assert!(estimate_tokens(part, "gpt-4o") <= 100);
+        }
+        // every line reaches exactly one part, in order
+        let sent: Vec<&str> = split
+            .parts
+            .iter()
+            .flat_map(|part| part.lines())
+            .filter(|line| line.starts_with("+line"))
+            .collect();
+        assert_eq!(sent, lines);
+    }
+
+    #[test]
+    fn test_files_are_left_out_last() {
+        let diff: String = (0..50)
+            .map(|i| file_diff(&format!("src/file{}.rs", i), &["+x"]))
+            .collect();
+        let fitted = fit_diff(&diff, "gpt-4o", 300);
+        assert!(fitted.tokens <= 300, "{}", fitted.tokens);
+        assert!(fitted.actions.last().unwrap().starts_with("left out "));
+        assert!(fitted.diff.contains("| +1 -0\n"));
+    }
+}
@@ -0,0 +1,316 @@
+use super::template::{Value, Variables};
+use crate::commit_formatter::{CommitType, LintRules, Linter};
+use crate::git::{GitClient, GitError};
+use log::warn;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::BTreeMap;
+
+/// How many commit subjects `{recent_commits}` holds
+const RECENT_COMMIT_COUNT: usize = 10;
+
+/// Commits read from the history for every example wanted, as many do not pass the rules
+const EXAMPLE_SCAN_FACTOR: usize = 10;
+
+// Tracker keys such as `PROJ-123`
+static ISSUE_KEY_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b([A-Z][A-Z0-9]+-\d+)\b").unwrap());
+
+// A number leading a branch name segment, e.g. `fix/42-crash`
+static ISSUE_NUMBER_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?:^|/)#?(\d+)(?:[-_]|$)").unwrap());
+
+/// Everything a prompt template can refer to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PromptContext {
+    pub diff: String,
+    pub branch: String,
+    pub staged_files: Vec<String>,
+    pub diffstat: String,
+    /// Subjects of the latest commits, newest first
+    pub recent_commits: Vec<String>,
+    /// Issue reference taken from the branch name, e.g. `PROJ-123` or `#42`
+    pub issue_id: Option<String>,
+    pub repo_name: String,
+    /// Main language of the staged files
+    pub language: Option<String>,
+    /// Well-formed messages from the repository's history, see `add_example_commits`
+    pub example_commits: Vec<String>,
+    /// Summaries of the parts of a large diff, see `AIClient::add_change_summaries`
+    pub change_summaries: Vec<String>,
+}
+
+impl PromptContext {
+    /// Names of the variables available to prompt templates
+    pub const VARIABLES: &'static [&'static str] = &[
+        "diff",
+        "branch",
+        "staged_files",
+        "diffstat",
+        "recent_commits",
+        "issue_id",
+        "repo_name",
+        "language",
+        "example_commits",
+        "change_summaries",
+    ];
+
+    /// A context holding only the diff
+    pub fn from_diff(diff: &str) -> Self {
+        PromptContext {
+            diff: diff.to_string(),
+            ..PromptContext::default()
+        }
+    }
+
+    /// Collect the context of the staged changes. Details git cannot provide, such as
+    /// the branch of a repository without commits, are left empty.
+    pub fn gather(git_client: &GitClient, diff: &str) -> Result<Self, GitError> {
+        let staged_files: Vec<String> = git_client
+            .get_staged_files()?
+            .lines()
+            .map(str::to_string)
+            .collect();
+        // A detached HEAD, or a repository without commits, has no branch name
+        let branch = match git_client.get_current_branch() {
+            Ok(branch) if branch != "HEAD" => branch,
+            Ok(_) => String::new(),
+            Err(e) => {
+                warn!("Could not read the current branch: {}", e);
+                String::new()
+            }
+        };
+        let repo_name = git_client
+            .get_toplevel()?
+            .and_then(|toplevel| {
+                toplevel
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            })
+            .unwrap_or_default();
+
+        Ok(PromptContext {
+            diff: diff.to_string(),
+            issue_id: issue_id(&branch),
+            language: language(&staged_files),
+            branch,
+            diffstat: git_client.get_diffstat()?,
+            recent_commits: git_client.get_recent_commits(RECENT_COMMIT_COUNT)?,
+            staged_files,
+            repo_name,
+            example_commits: Vec::new(),
+            change_summaries: Vec::new(),
+        })
+    }
+
+    /// Pick up to `count` recent commit messages that follow the lint rules without a
+    /// single warning, to show the model the team's conventions. With `match_paths`,
+    /// commits touching the directories of the staged files come first.
+    pub fn add_example_commits(
+        &mut self,
+        git_client: &GitClient,
+        count: usize,
+        match_paths: bool,
+        rules: &LintRules,
+    ) -> Result<(), GitError> {
+        let linter = Linter::new(rules.clone());
+        let well_formed = |message: &String| {
+            CommitType::new(message.clone()).parse().is_ok() && linter.lint(message).is_clean()
+        };
+
+        let mut path_sets = Vec::new();
+        if match_paths && !self.staged_files.is_empty() {
+            path_sets.push(directories(&self.staged_files));
+        }
+        path_sets.push(Vec::new());
+
+        let mut examples: Vec<String> = Vec::new();
+        for paths in path_sets {
+            for message in git_client.get_commit_messages(count * EXAMPLE_SCAN_FACTOR, &paths)? {
+                if examples.len() == count {
+                    break;
+                }
+                if well_formed(&message) && !examples.contains(&message) {
+                    examples.push(message);
+                }
+            }
+        }
+        self.example_commits = examples;
+        Ok(())
+    }
+
+    pub fn variables(&self) -> Variables {
+        let values: [(&str, Value); 10] = [
+            ("diff", self.diff.as_str().into()),
+            ("branch", self.branch.as_str().into()),
+            ("staged_files", self.staged_files.clone().into()),
+            ("diffstat", self.diffstat.as_str().into()),
+            ("recent_commits", self.recent_commits.clone().into()),
+            ("issue_id", self.issue_id.clone().into()),
+            ("repo_name", self.repo_name.as_str().into()),
+            ("language", self.language.clone().into()),
+            ("example_commits", self.example_commits.clone().into()),
+            ("change_summaries", self.change_summaries.clone().into()),
+        ];
+        values
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect()
+    }
+}
+
+/// The directories holding the files; files at the top level stand for themselves
+fn directories(files: &[String]) -> Vec<String> {
+    let mut directories: Vec<String> = files
+        .iter()
+        .map(|file| match file.rsplit_once('/') {
+            Some((directory, _)) => directory.to_string(),
+            None => file.clone(),
+        })
+        .collect();
+    directories.sort();
+    directories.dedup();
+    directories
+}
+
+/// The issue a branch is named after: a tracker key (`feature/PROJ-123-login`) or a
+/// leading number (`fix/42-crash` gives `#42`)
+fn issue_id(branch: &str) -> Option<String> {
+    if let Some(captures) = ISSUE_KEY_REGEX.captures(branch) {
+        return Some(captures[1].to_string());
+    }
+    ISSUE_NUMBER_REGEX
+        .captures(branch)
+        .map(|captures| format!("#{}", &captures[1]))
+}
+
+/// The language most of the files are written in, judged by their extensions
+fn language(files: &[String]) -> Option<String> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for file in files {
+        let extension = file.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+        let language = match extension.to_lowercase().as_str() {
+            "rs" => "Rust",
+            "py" => "Python",
+            "js" | "mjs" | "cjs" | "jsx" => "JavaScript",
+            "ts" | "tsx" => "TypeScript",
+            "go" => "Go",
+            "java" => "Java",
+            "kt" | "kts" => "Kotlin",
+            "swift" => "Swift",
+            "rb" => "Ruby",
+            "php" => "PHP",
+            "cs" => "C#",
+            "c" | "h" => "C",
+            "cc" | "cpp" | "cxx" | "hpp" => "C++",
+            "sh" | "bash" => "Shell",
+            "md" => "Markdown",
+            _ => continue,
+        };
+        *counts.entry(language).or_default() += 1;
+    }
+
+    // Ties go to the alphabetically first language so the result is stable
+    counts
+        .into_iter()
+        .fold(
+            None,
+            |best: Option<(&str, usize)>, (language, count)| match best {
+                Some((_, best_count)) if best_count >= count => best,
+                _ => Some((language, count)),
+            },
+        )
+        .map(|(language, _)| language.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_id_from_branch() {
+        assert_eq!(
+            issue_id("feature/PROJ-123-login").as_deref(),
+            Some("PROJ-123")
+        );
+        assert_eq!(issue_id("fix/42-crash").as_deref(), Some("#42"));
+        assert_eq!(issue_id("123_cleanup").as_deref(), Some("#123"));
+        assert_eq!(issue_id("main"), None);
+        assert_eq!(issue_id("release/v2"), None);
+    }
+
+    #[test]
+    fn test_language_of_staged_files() {
+        let files = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            language(&files(&["src/main.rs", "src/lib.rs", "readme.md"])).as_deref(),
+            Some("Rust")
+        );
+        assert_eq!(language(&files(&["Makefile", "LICENSE"])), None);
+    }
+
+    #[test]
+    fn test_add_example_commits() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .args(args)
+                .current_dir(dir.path())
+                .output()
+                .unwrap()
+        };
+        git(&["init"]);
+        git(&["config", "user.name", "Test User"]);
+        git(&["config", "user.email", "test@example.com"]);
+        for (file, message) in [
+            ("src/api/login.rs", "feat(api): add login"),
+            ("docs/guide.md", "docs: describe the login"),
+            ("docs/faq.md", "Updated the FAQ."),
+            ("readme.md", "docs(readme): add badges"),
+        ] {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, message).unwrap();
+            git(&["add", file]);
+            git(&["commit", "-m", message]);
+        }
+        let git_client = GitClient::with_working_dir(dir.path().to_string_lossy().to_string());
+        let mut context = PromptContext {
+            staged_files: vec!["src/api/logout.rs".to_string()],
+            ..PromptContext::default()
+        };
+
+        context
+            .add_example_commits(&git_client, 2, false, &LintRules::default())
+            .unwrap();
+        assert_eq!(
+            context.example_commits,
+            ["docs(readme): add badges", "docs: describe the login"]
+        );
+
+        context
+            .add_example_commits(&git_client, 2, true, &LintRules::default())
+            .unwrap();
+        assert_eq!(
+            context.example_commits,
+            ["feat(api): add login", "docs(readme): add badges"]
+        );
+    }
+
+    #[test]
+    fn test_variables_cover_every_name() {
+        let variables = PromptContext::from_diff("+line").variables();
+        assert_eq!(
+            variables.keys().collect::<Vec<_>>().len(),
+            PromptContext::VARIABLES.len()
+        );
+        for name in PromptContext::VARIABLES {
+            assert!(variables.contains_key(*name));
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use super::{AIError, ErrorKind};
+use std::str::FromStr;
+
+/// Errors that move on to the next model of `fallbacks` when `fallback_on` is not set
+pub const DEFAULT_FALLBACK_ON: &[ErrorClass] = &[
+    ErrorClass::RateLimit,
+    ErrorClass::Server,
+    ErrorClass::Timeout,
+    ErrorClass::Connection,
+];
+
+/// A kind of failure that can be named in `fallback_on`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// 429 Too Many Requests
+    RateLimit,
+    /// 5xx answers
+    Server,
+    /// No answer within `request_timeout`
+    Timeout,
+    /// The endpoint could not be reached
+    Connection,
+    /// 401 and 403, e.g. an expired key or a model the key may not use
+    Auth,
+    /// 404, usually a model the endpoint doesn't serve
+    NotFound,
+    /// Any failure at all
+    Any,
+}
+
+impl FromStr for ErrorClass {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim().to_lowercase().replace('-', "_").as_str() {
+            "rate_limit" => Ok(ErrorClass::RateLimit),
+            "server" => Ok(ErrorClass::Server),
+            "timeout" => Ok(ErrorClass::Timeout),
+            "connection" => Ok(ErrorClass::Connection),
+            "auth" => Ok(ErrorClass::Auth),
+            "not_found" => Ok(ErrorClass::NotFound),
+            "any" => Ok(ErrorClass::Any),
+            other => Err(format!(
+                "Unknown error class '{}'. Supported classes: rate_limit, server, timeout, connection, auth, not_found, any",
+                other
+            )),
+        }
+    }
+}
+
+impl ErrorClass {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorClass::RateLimit => "rate_limit",
+            ErrorClass::Server => "server",
+            ErrorClass::Timeout => "timeout",
+            ErrorClass::Connection => "connection",
+            ErrorClass::Auth => "auth",
+            ErrorClass::NotFound => "not_found",
+            ErrorClass::Any => "any",
+        }
+    }
+
+    /// Whether `error` belongs to this class
+    pub fn matches(&self, error: &AIError) -> bool {
+        let status = match error.kind {
+            ErrorKind::Http { status, .. } => Some(status),
+            _ => None,
+        };
+        match self {
+            ErrorClass::RateLimit => status == Some(429),
+            ErrorClass::Server => status.is_some_and(|status| (500..600).contains(&status)),
+            ErrorClass::Timeout => error.kind == ErrorKind::Timeout || status == Some(408),
+            ErrorClass::Connection => error.kind == ErrorKind::Connection,
+            ErrorClass::Auth => matches!(status, Some(401 | 403)),
+            ErrorClass::NotFound => status == Some(404),
+            ErrorClass::Any => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn http_error(status: u16) -> AIError {
+        AIError::new("error").with_kind(ErrorKind::Http {
+            status,
+            retry_after: None,
+        })
+    }
+
+    #[test]
+    fn test_error_classes() {
+        assert!(ErrorClass::RateLimit.matches(&http_error(429)));
+        assert!(ErrorClass::Server.matches(&http_error(502)));
+        assert!(!ErrorClass::Server.matches(&http_error(429)));
+        assert!(ErrorClass::Auth.matches(&http_error(401)));
+        assert!(ErrorClass::NotFound.matches(&http_error(404)));
+        assert!(ErrorClass::Timeout.matches(&AIError::new("slow").with_kind(ErrorKind::Timeout)));
+        assert!(!ErrorClass::Connection.matches(&AIError::new("Invalid prompt")));
+        assert!(ErrorClass::Any.matches(&AIError::new("Invalid prompt")));
+
+        assert_eq!(
+            "Rate-Limit".parse::<ErrorClass>().unwrap(),
+            ErrorClass::RateLimit
+        );
+        assert_eq!(ErrorClass::NotFound.name(), "not_found");
+        assert!("teapot".parse::<ErrorClass>().is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use super::provider::{CommitModelProvider, CompletionRequest, ProviderFuture};
+use super::AIError;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How recorded request/response fixtures are used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureMode {
+    /// Forward requests to the real provider and save each exchange
+    Record,
+    /// Answer requests from saved exchanges without touching the network
+    Replay,
+}
+
+impl FixtureMode {
+    pub fn from_name(name: &str) -> Result<Self, AIError> {
+        match name.trim().to_lowercase().as_str() {
+            "record" => Ok(FixtureMode::Record),
+            "replay" => Ok(FixtureMode::Replay),
+            other => Err(AIError::new(format!(
+                "Unknown fixture mode '{}'. Supported modes: record, replay",
+                other
+            ))),
+        }
+    }
+}
+
+/// A single recorded exchange as stored on disk
+#[derive(Debug, Serialize, Deserialize)]
+struct Fixture {
+    request: CompletionRequest,
+    responses: Vec<String>,
+}
+
+/// Path of the fixture file for a request.
+///
+/// The file name is an FNV-1a hash of the serialised request so the same
+/// prompt, diff and model always map to the same fixture.
+pub fn fixture_path(dir: &Path, request: &CompletionRequest) -> Result<PathBuf, AIError> {
+    let key = serde_json::to_string(request)
+        .map_err(|e| AIError::new(format!("Failed to serialise request for fixture: {}", e)))?;
+
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in key.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    Ok(dir.join(format!("{:016x}.json", hash)))
+}
+
+/// Wraps a provider and saves every request/response pair to a fixture directory
+pub struct RecordingProvider {
+    inner: Box<dyn CommitModelProvider>,
+    dir: PathBuf,
+}
+
+impl RecordingProvider {
+    pub fn new(inner: Box<dyn CommitModelProvider>, dir: PathBuf) -> Self {
+        RecordingProvider { inner, dir }
+    }
+}
+
+impl CommitModelProvider for RecordingProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn complete<'a>(&'a self, request: &'a CompletionRequest) -> ProviderFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            let responses = self.inner.complete(request).await?;
+
+            let path = fixture_path(&self.dir, request)?;
+            let fixture = Fixture {
+                request: request.clone(),
+                responses: responses.clone(),
+            };
+            let json = serde_json::to_string_pretty(&fixture)
+                .map_err(|e| AIError::new(format!("Failed to serialise fixture: {}", e)))?;
+            fs::create_dir_all(&self.dir).map_err(|e| {
+                AIError::new(format!(
+                    "Failed to create fixture directory {:?}: {}",
+                    self.dir, e
+                ))
+            })?;
+            fs::write(&path, json)
+                .map_err(|e| AIError::new(format!("Failed to write fixture {:?}: {}", path, e)))?;
+            info!("Recorded fixture {:?}", path);
+
+            Ok(responses)
+        })
+    }
+
+    fn list_models(&self) -> ProviderFuture<'_, Vec<String>> {
+        self.inner.list_models()
+    }
+}
+
+/// Answers requests from fixtures saved by `RecordingProvider`
+pub struct ReplayProvider {
+    dir: PathBuf,
+}
+
+impl ReplayProvider {
+    pub fn new(dir: PathBuf) -> Self {
+        ReplayProvider { dir }
+    }
+}
+
+impl CommitModelProvider for ReplayProvider {
+    fn name(&self) -> &str {
+        "replay"
+    }
+
+    fn complete<'a>(&'a self, request: &'a CompletionRequest) -> ProviderFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            let path = fixture_path(&self.dir, request)?;
+            let json = fs::read_to_string(&path).map_err(|e| AIError::new(format!(
+                    "No recorded fixture for this request at {:?} ({}). Run once with IAC_FIXTURE_MODE=record to create it.",
+                    path, e
+                )))?;
+            let fixture: Fixture = serde_json::from_str(&json)
+                .map_err(|e| AIError::new(format!("Failed to parse fixture {:?}: {}", path, e)))?;
+            info!("Replayed fixture {:?}", path);
+
+            Ok(fixture.responses)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{ChatMessage, MockProvider};
+    use tempfile::TempDir;
+
+    fn request(content: &str) -> CompletionRequest {
+        CompletionRequest::new("mock-model".to_string(), vec![ChatMessage::user(content)])
+    }
+
+    #[test]
+    fn test_fixture_mode_from_name() {
+        assert_eq!(
+            FixtureMode::from_name("record").unwrap(),
+            FixtureMode::Record
+        );
+        assert_eq!(
+            FixtureMode::from_name("Replay").unwrap(),
+            FixtureMode::Replay
+        );
+        assert!(FixtureMode::from_name("rewind").is_err());
+    }
+
+    #[test]
+    fn test_fixture_path_is_stable_per_request() {
+        let dir = Path::new("fixtures");
+        let a = fixture_path(dir, &request("same")).unwrap();
+        let b = fixture_path(dir, &request("same")).unwrap();
+        let c = fixture_path(dir, &request("different")).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let temp_dir = TempDir::new().unwrap();
+        let recorder = RecordingProvider::new(
+            Box::new(MockProvider::new(vec!["feat: recorded".to_string()])),
+            temp_dir.path().to_path_buf(),
+        );
+        let recorded = recorder.complete(&request("diff")).await.unwrap();
+        assert_eq!(recorded, ["feat: recorded"]);
+
+        let replayer = ReplayProvider::new(temp_dir.path().to_path_buf());
+        let replayed = replayer.complete(&request("diff")).await.unwrap();
+        assert_eq!(replayed, recorded);
+    }
+
+    #[tokio::test]
+    async fn test_replay_missing_fixture() {
+        let temp_dir = TempDir::new().unwrap();
+        let replayer = ReplayProvider::new(temp_dir.path().to_path_buf());
+        let error = replayer.complete(&request("unseen")).await.unwrap_err();
+        assert!(error.message.contains("No recorded fixture"));
+    }
+}
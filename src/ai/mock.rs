@@ -0,0 +1,92 @@
+use super::provider::{CommitModelProvider, CompletionRequest, ProviderFuture};
+use std::sync::Mutex;
+
+/// Response returned by the mock provider when none is configured
+pub const DEFAULT_MOCK_RESPONSE: &str =
+    "<commit_message>\nchore: update staged changes\n</commit_message>";
+
+/// Deterministic offline provider for tests and dry runs.
+///
+/// Responses are handed out in order, repeating the last one once the list is
+/// exhausted, and every request is kept so tests can inspect what was sent.
+pub struct MockProvider {
+    responses: Vec<String>,
+    requests: Mutex<Vec<CompletionRequest>>,
+}
+
+impl MockProvider {
+    pub fn new(responses: Vec<String>) -> Self {
+        let responses = if responses.is_empty() {
+            vec![DEFAULT_MOCK_RESPONSE.to_string()]
+        } else {
+            responses
+        };
+
+        MockProvider {
+            responses,
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Requests received so far, oldest first
+    pub fn requests(&self) -> Vec<CompletionRequest> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+}
+
+impl Default for MockProvider {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl CommitModelProvider for MockProvider {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    fn complete<'a>(&'a self, request: &'a CompletionRequest) -> ProviderFuture<'a, Vec<String>> {
+        let mut requests = self
+            .requests
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let index = requests.len().min(self.responses.len() - 1);
+        requests.push(request.clone());
+        let response = self.responses[index].clone();
+
+        Box::pin(async move { Ok(vec![response]) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::ChatMessage;
+
+    fn request(content: &str) -> CompletionRequest {
+        CompletionRequest::new("mock-model".to_string(), vec![ChatMessage::user(content)])
+    }
+
+    #[tokio::test]
+    async fn test_mock_provider_returns_responses_in_order() {
+        let provider = MockProvider::new(vec!["first".to_string(), "second".to_string()]);
+
+        assert_eq!(provider.complete(&request("a")).await.unwrap(), ["first"]);
+        assert_eq!(provider.complete(&request("b")).await.unwrap(), ["second"]);
+        assert_eq!(provider.complete(&request("c")).await.unwrap(), ["second"]);
+
+        let requests = provider.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[1].messages[0].content, "b");
+    }
+
+    #[tokio::test]
+    async fn test_mock_provider_default_response() {
+        let provider = MockProvider::default();
+        let responses = provider.complete(&request("diff")).await.unwrap();
+        assert_eq!(responses, [DEFAULT_MOCK_RESPONSE]);
+    }
+}
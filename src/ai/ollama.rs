@@ -0,0 +1,290 @@
+use super::provider::{ChatMessage, CommitModelProvider, CompletionRequest, ProviderFuture, Role};
+use super::{AIError, ErrorKind};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+
+/// Default address of a local `ollama serve`
+pub const DEFAULT_OLLAMA_ENDPOINT: &str = "http://localhost:11434";
+
+/// Default model pulled by most Ollama installs
+pub const DEFAULT_OLLAMA_MODEL: &str = "llama3.2";
+
+/// Provider talking to a local Ollama server through its native HTTP API,
+/// so diffs never leave the machine
+pub struct OllamaProvider {
+    client: reqwest::Client,
+    endpoint: String,
+}
+
+#[derive(Serialize)]
+struct OllamaChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<OllamaMessage>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<OllamaOptions>,
+}
+
+/// Model parameters; Ollama calls the token limit `num_predict`
+#[derive(Serialize)]
+struct OllamaOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OllamaMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct OllamaChatResponse {
+    message: OllamaMessage,
+}
+
+#[derive(Deserialize)]
+struct OllamaTagsResponse {
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Deserialize)]
+struct OllamaModel {
+    name: String,
+}
+
+impl OllamaProvider {
+    pub fn new(endpoint: Option<String>) -> Self {
+        let endpoint = endpoint
+            .unwrap_or_else(|| DEFAULT_OLLAMA_ENDPOINT.to_string())
+            .trim_end_matches('/')
+            .to_string();
+        info!("Using Ollama endpoint: {}", endpoint);
+
+        OllamaProvider {
+            client: reqwest::Client::new(),
+            endpoint,
+        }
+    }
+
+    fn to_ollama_message(message: &ChatMessage) -> OllamaMessage {
+        let role = match message.role {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        };
+
+        OllamaMessage {
+            role: role.to_string(),
+            content: message.content.clone(),
+        }
+    }
+
+    /// Turn a transport error into a message that says what to do about it
+    fn request_error(&self, e: reqwest::Error) -> AIError {
+        error!("Ollama request failed: {}", e);
+        let kind = ErrorKind::from_request_error(&e);
+        if e.is_connect() {
+            AIError::new(format!(
+                "Could not connect to Ollama at {}. Is the server running? Start it with 'ollama serve' or set IAC_OLLAMA_ENDPOINT.",
+                self.endpoint
+            ))
+            .with_kind(kind)
+        } else {
+            AIError::new(format!("Ollama API error: {}", e)).with_kind(kind)
+        }
+    }
+
+    async fn read_response(response: reqwest::Response) -> Result<String, AIError> {
+        let status = response.status();
+        let kind = ErrorKind::from_response(&response);
+        let body = response.text().await.map_err(|e| {
+            AIError::new(format!("Failed to read Ollama response: {}", e))
+                .with_kind(ErrorKind::from_request_error(&e))
+        })?;
+
+        if !status.is_success() {
+            return Err(
+                AIError::new(format!("Ollama API error: {}: {}", status, body)).with_kind(kind),
+            );
+        }
+
+        Ok(body)
+    }
+}
+
+impl CommitModelProvider for OllamaProvider {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    fn complete<'a>(&'a self, request: &'a CompletionRequest) -> ProviderFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            let body = OllamaChatRequest {
+                model: &request.model,
+                messages: request
+                    .messages
+                    .iter()
+                    .map(Self::to_ollama_message)
+                    .collect(),
+                stream: false,
+                options: (request.temperature.is_some() || request.max_tokens.is_some()).then_some(
+                    OllamaOptions {
+                        temperature: request.temperature,
+                        num_predict: request.max_tokens,
+                    },
+                ),
+            };
+
+            let response = self
+                .client
+                .post(format!("{}/api/chat", self.endpoint))
+                .json(&body)
+                .send()
+                .await
+                .map_err(|e| self.request_error(e))?;
+            let text = Self::read_response(response).await?;
+
+            let parsed: OllamaChatResponse = serde_json::from_str(&text).map_err(|e| {
+                AIError::new(format!(
+                    "Failed to parse Ollama response: {} / response {}",
+                    e, text
+                ))
+            })?;
+
+            Ok(vec![parsed.message.content])
+        })
+    }
+
+    fn list_models(&self) -> ProviderFuture<'_, Vec<String>> {
+        Box::pin(async move {
+            let response = self
+                .client
+                .get(format!("{}/api/tags", self.endpoint))
+                .send()
+                .await
+                .map_err(|e| self.request_error(e))?;
+            let text = Self::read_response(response).await?;
+
+            let parsed: OllamaTagsResponse = serde_json::from_str(&text).map_err(|e| {
+                AIError::new(format!(
+                    "Failed to parse Ollama model list: {} / response {}",
+                    e, text
+                ))
+            })?;
+
+            Ok(parsed.models.into_iter().map(|model| model.name).collect())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serve a single canned JSON response and hand back the request body
+    fn stand_in_server(response_body: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some(value) = header.to_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response_body.len(),
+                response_body
+            )
+            .unwrap();
+
+            format!("{}{}", request_line, String::from_utf8(body).unwrap())
+        });
+
+        (endpoint, handle)
+    }
+
+    #[tokio::test]
+    async fn test_ollama_chat_completion() {
+        let (endpoint, server) = stand_in_server(
+            r#"{"model":"llama3.2","message":{"role":"assistant","content":"feat: local model"},"done":true}"#,
+        );
+        let provider = OllamaProvider::new(Some(endpoint));
+        let request = CompletionRequest::new(
+            "llama3.2".to_string(),
+            vec![ChatMessage::system("system"), ChatMessage::user("diff")],
+        );
+
+        let responses = provider.complete(&request).await.unwrap();
+        assert_eq!(responses, ["feat: local model"]);
+
+        let received = server.join().unwrap();
+        assert!(received.starts_with("POST /api/chat"));
+        assert!(received.contains(r#""stream":false"#));
+        assert!(received.contains(r#""role":"system""#));
+        assert!(!received.contains("options"));
+    }
+
+    #[tokio::test]
+    async fn test_ollama_sampling_options() {
+        let (endpoint, server) = stand_in_server(
+            r#"{"model":"llama3.2","message":{"role":"assistant","content":"fix: x"},"done":true}"#,
+        );
+        let provider = OllamaProvider::new(Some(endpoint));
+        let request = CompletionRequest::new("llama3.2".to_string(), vec![ChatMessage::user("x")])
+            .with_sampling(Some(0.2), Some(200));
+
+        provider.complete(&request).await.unwrap();
+        let received = server.join().unwrap();
+        assert!(received.contains(r#""options":{"temperature":0.2,"num_predict":200}"#));
+    }
+
+    #[tokio::test]
+    async fn test_ollama_list_models() {
+        let (endpoint, server) =
+            stand_in_server(r#"{"models":[{"name":"llama3.2:latest"},{"name":"qwen2.5-coder"}]}"#);
+        let provider = OllamaProvider::new(Some(format!("{}/", endpoint)));
+
+        let models = provider.list_models().await.unwrap();
+        assert_eq!(models, ["llama3.2:latest", "qwen2.5-coder"]);
+        assert!(server.join().unwrap().starts_with("GET /api/tags"));
+    }
+
+    #[tokio::test]
+    async fn test_ollama_not_running() {
+        // Bind then drop a listener to get a port nothing is listening on
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let provider = OllamaProvider::new(Some(format!("http://127.0.0.1:{}", port)));
+        let request = CompletionRequest::new("llama3.2".to_string(), vec![ChatMessage::user("x")]);
+
+        let error = provider.complete(&request).await.unwrap_err();
+        assert!(error.message.contains("Could not connect to Ollama"));
+        assert_eq!(error.kind, ErrorKind::Connection);
+    }
+}
@@ -0,0 +1,227 @@
+use super::provider::{ChatMessage, CommitModelProvider, CompletionRequest, ProviderFuture, Role};
+use super::{AIError, ErrorKind};
+use log::{error, info};
+use openai_api_rs::v1::chat_completion::{
+    self, ChatCompletionRequest, ChatCompletionResponse, Content, MessageRole,
+};
+use std::env;
+
+/// Endpoint the OpenAI client talks to when none is configured
+pub const DEFAULT_OPENAI_ENDPOINT: &str = "https://api.openai.com/v1";
+
+/// Provider for OpenAI and any endpoint speaking the OpenAI chat completions API
+/// (OpenRouter, Azure-style gateways, LiteLLM, ...).
+///
+/// Requests are sent with reqwest rather than `OpenAIClient`, which drops the status
+/// and `Retry-After` header of failed requests that retries depend on.
+pub struct OpenAIProvider {
+    client: reqwest::Client,
+    api_key: String,
+    endpoint: String,
+}
+
+impl OpenAIProvider {
+    pub fn new(api_key: String, endpoint: Option<String>) -> Result<Self, AIError> {
+        let endpoint = match endpoint {
+            Some(endpoint) => {
+                info!("Using custom OpenAI endpoint: {}", endpoint);
+                endpoint
+            }
+            None => {
+                env::var("OPENAI_API_BASE").unwrap_or_else(|_| DEFAULT_OPENAI_ENDPOINT.to_string())
+            }
+        };
+
+        let client = reqwest::Client::builder().build().map_err(|e| {
+            error!("Failed to create OpenAI client: {}", e);
+            AIError::new(format!("Failed to create OpenAI client: {}", e))
+        })?;
+
+        Ok(OpenAIProvider {
+            client,
+            api_key,
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+        })
+    }
+
+    fn to_openai_message(message: &ChatMessage) -> chat_completion::ChatCompletionMessage {
+        let role = match message.role {
+            Role::System => MessageRole::system,
+            Role::User => MessageRole::user,
+            Role::Assistant => MessageRole::assistant,
+        };
+
+        chat_completion::ChatCompletionMessage {
+            role,
+            content: Content::Text(message.content.clone()),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+}
+
+impl CommitModelProvider for OpenAIProvider {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    fn complete<'a>(&'a self, request: &'a CompletionRequest) -> ProviderFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            let messages = request
+                .messages
+                .iter()
+                .map(Self::to_openai_message)
+                .collect();
+            let mut req = ChatCompletionRequest::new(request.model.clone(), messages);
+            if request.candidates > 1 {
+                req.n = Some(i64::from(request.candidates));
+            }
+            req.temperature = request.temperature;
+            req.max_tokens = request.max_tokens.map(i64::from);
+
+            let response = self
+                .client
+                .post(format!("{}/chat/completions", self.endpoint))
+                .bearer_auth(&self.api_key)
+                .header("X-Title", "IAmCommitted")
+                .header("Referer", "https://iamcommitted.glyndarkin.co.uk/")
+                .json(&req)
+                .send()
+                .await
+                .map_err(|e| {
+                    AIError::new(format!("OpenAI API error: {}", e))
+                        .with_kind(ErrorKind::from_request_error(&e))
+                })?;
+            let status = response.status();
+            let kind = ErrorKind::from_response(&response);
+            let text = response.text().await.map_err(|e| {
+                AIError::new(format!("Failed to read OpenAI response: {}", e))
+                    .with_kind(ErrorKind::from_request_error(&e))
+            })?;
+            if !status.is_success() {
+                return Err(
+                    AIError::new(format!("OpenAI API error: {}: {}", status, text)).with_kind(kind),
+                );
+            }
+            let result: ChatCompletionResponse = serde_json::from_str(&text).map_err(|e| {
+                AIError::new(format!(
+                    "Failed to parse OpenAI response: {} / response {}",
+                    e, text
+                ))
+            })?;
+
+            let completions: Vec<String> = result
+                .choices
+                .into_iter()
+                .filter_map(|choice| choice.message.content)
+                .collect();
+
+            if completions.is_empty() {
+                return Err(AIError::new("No content in OpenAI response".to_string()));
+            }
+
+            Ok(completions)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    /// Answer a single request with `status` and `headers`, handing back what was received
+    fn stand_in_server(
+        status: &'static str,
+        headers: &'static str,
+        body: &'static str,
+    ) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/v1", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut received = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+                received.push_str(&line);
+                if line.trim().is_empty() {
+                    break;
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                headers,
+                body.len(),
+                body
+            )
+            .unwrap();
+
+            received + &String::from_utf8(request_body).unwrap()
+        });
+
+        (endpoint, handle)
+    }
+
+    fn request() -> CompletionRequest {
+        CompletionRequest::new("gpt-4o-mini".to_string(), vec![ChatMessage::user("diff")])
+    }
+
+    #[tokio::test]
+    async fn test_openai_chat_completion() {
+        let (endpoint, server) = stand_in_server(
+            "200 OK",
+            "",
+            r#"{"id":"1","object":"chat.completion","created":0,"model":"gpt-4o-mini","choices":[{"index":0,"message":{"role":"assistant","content":"feat: add x"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2},"system_fingerprint":null}"#,
+        );
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(endpoint)).unwrap();
+
+        assert_eq!(
+            provider.complete(&request()).await.unwrap(),
+            ["feat: add x"]
+        );
+        let received = server.join().unwrap().to_lowercase();
+        assert!(received.starts_with("post /v1/chat/completions"));
+        assert!(received.contains("authorization: bearer test-key"));
+        assert!(received.contains("x-title: iamcommitted"));
+    }
+
+    #[tokio::test]
+    async fn test_openai_rate_limit() {
+        let (endpoint, server) = stand_in_server(
+            "429 Too Many Requests",
+            "Retry-After: 3\r\n",
+            r#"{"error":{"message":"Rate limit reached"}}"#,
+        );
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(endpoint)).unwrap();
+
+        let error = provider.complete(&request()).await.unwrap_err();
+        server.join().unwrap();
+        assert!(error.message.contains("429 Too Many Requests"));
+        assert!(error.message.contains("Rate limit reached"));
+        assert_eq!(
+            error.kind,
+            ErrorKind::Http {
+                status: 429,
+                retry_after: Some(Duration::from_secs(3))
+            }
+        );
+        assert!(error.is_transient());
+    }
+}
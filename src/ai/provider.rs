@@ -0,0 +1,237 @@
+use super::ollama::{DEFAULT_OLLAMA_ENDPOINT, DEFAULT_OLLAMA_MODEL};
+use super::openai::DEFAULT_OPENAI_ENDPOINT;
+use super::AIError;
+use openai_api_rs::v1::common::GPT4_O_MINI;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Boxed future returned by provider calls so the trait stays object safe
+pub type ProviderFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, AIError>> + Send + 'a>>;
+
+/// Role of a message in a chat conversation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+/// A single provider-agnostic chat message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        ChatMessage {
+            role: Role::System,
+            content: content.into(),
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        ChatMessage {
+            role: Role::User,
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        ChatMessage {
+            role: Role::Assistant,
+            content: content.into(),
+        }
+    }
+}
+
+/// A chat completion request sent to a provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompletionRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    /// Number of alternative completions wanted; providers may return fewer
+    pub candidates: u32,
+    /// Sampling temperature; the provider default when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    /// Upper bound on generated tokens; the provider default when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+impl CompletionRequest {
+    pub fn new(model: String, messages: Vec<ChatMessage>) -> Self {
+        CompletionRequest {
+            model,
+            messages,
+            candidates: 1,
+            temperature: None,
+            max_tokens: None,
+        }
+    }
+
+    pub fn with_sampling(mut self, temperature: Option<f64>, max_tokens: Option<u32>) -> Self {
+        self.temperature = temperature;
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    pub fn with_candidates(mut self, candidates: u32) -> Self {
+        self.candidates = candidates.max(1);
+        self
+    }
+}
+
+/// A backend capable of turning chat messages into completions.
+///
+/// Implement this to plug in a model provider (a native Anthropic client, a
+/// local server, an in-house gateway) and hand it to `AIClient::with_provider`.
+pub trait CommitModelProvider: Send + Sync {
+    /// Short provider name used in logs
+    fn name(&self) -> &str;
+
+    /// Send the messages and return every completion the provider produced
+    fn complete<'a>(&'a self, request: &'a CompletionRequest) -> ProviderFuture<'a, Vec<String>>;
+
+    /// List the models the provider can serve, if it supports discovery
+    fn list_models(&self) -> ProviderFuture<'_, Vec<String>> {
+        let message = format!(
+            "The {} provider does not support listing models",
+            self.name()
+        );
+        Box::pin(async move { Err(AIError::new(message)) })
+    }
+}
+
+impl<T: CommitModelProvider + ?Sized> CommitModelProvider for Arc<T> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn complete<'a>(&'a self, request: &'a CompletionRequest) -> ProviderFuture<'a, Vec<String>> {
+        (**self).complete(request)
+    }
+
+    fn list_models(&self) -> ProviderFuture<'_, Vec<String>> {
+        (**self).list_models()
+    }
+}
+
+/// The providers IAmCommitted knows how to build from configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderKind {
+    OpenAI,
+    Ollama,
+    Mock,
+}
+
+impl ProviderKind {
+    pub fn from_name(name: &str) -> Result<Self, AIError> {
+        match name.trim().to_lowercase().as_str() {
+            "openai" | "openai-compatible" => Ok(ProviderKind::OpenAI),
+            "ollama" => Ok(ProviderKind::Ollama),
+            "mock" => Ok(ProviderKind::Mock),
+            other => Err(AIError::new(format!(
+                "Unknown AI provider '{}'. Supported providers: openai, ollama, mock",
+                other
+            ))),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProviderKind::OpenAI => "openai",
+            ProviderKind::Ollama => "ollama",
+            ProviderKind::Mock => "mock",
+        }
+    }
+
+    /// Whether the provider needs an API key to be configured
+    pub fn requires_api_key(&self) -> bool {
+        match self {
+            ProviderKind::OpenAI => true,
+            ProviderKind::Ollama | ProviderKind::Mock => false,
+        }
+    }
+
+    /// Model used when none is configured
+    pub fn default_model(&self) -> &'static str {
+        match self {
+            ProviderKind::OpenAI => GPT4_O_MINI,
+            ProviderKind::Ollama => DEFAULT_OLLAMA_MODEL,
+            ProviderKind::Mock => "mock",
+        }
+    }
+
+    /// Endpoint used when none is configured, `None` for providers without one
+    pub fn default_endpoint(&self) -> Option<&'static str> {
+        match self {
+            ProviderKind::OpenAI => Some(DEFAULT_OPENAI_ENDPOINT),
+            ProviderKind::Ollama => Some(DEFAULT_OLLAMA_ENDPOINT),
+            ProviderKind::Mock => None,
+        }
+    }
+
+    /// Prefix of the environment variables for this provider, e.g. `OPENAI`
+    pub fn env_prefix(&self) -> &'static str {
+        match self {
+            ProviderKind::OpenAI => "OPENAI",
+            ProviderKind::Ollama => "OLLAMA",
+            ProviderKind::Mock => "MOCK",
+        }
+    }
+
+    /// Read a provider setting - `IAC_<PREFIX>_<NAME>` takes precedence over `<PREFIX>_<NAME>`
+    pub fn env_var(&self, name: &str) -> Result<String, env::VarError> {
+        let prefix = self.env_prefix();
+        env::var(format!("IAC_{}_{}", prefix, name))
+            .or_else(|_| env::var(format!("{}_{}", prefix, name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_kind_from_name() {
+        assert_eq!(
+            ProviderKind::from_name("openai").unwrap(),
+            ProviderKind::OpenAI
+        );
+        assert_eq!(
+            ProviderKind::from_name(" OpenAI ").unwrap(),
+            ProviderKind::OpenAI
+        );
+        assert!(ProviderKind::from_name("unknown").is_err());
+    }
+
+    #[test]
+    fn test_provider_env_var_precedence() {
+        let _env = crate::test_support::env_lock();
+        env::remove_var("IAC_OPENAI_ORGANIZATION");
+        env::remove_var("OPENAI_ORGANIZATION");
+        assert!(ProviderKind::OpenAI.env_var("ORGANIZATION").is_err());
+
+        env::set_var("OPENAI_ORGANIZATION", "fallback");
+        assert_eq!(
+            ProviderKind::OpenAI.env_var("ORGANIZATION").unwrap(),
+            "fallback"
+        );
+
+        env::set_var("IAC_OPENAI_ORGANIZATION", "preferred");
+        assert_eq!(
+            ProviderKind::OpenAI.env_var("ORGANIZATION").unwrap(),
+            "preferred"
+        );
+
+        env::remove_var("IAC_OPENAI_ORGANIZATION");
+        env::remove_var("OPENAI_ORGANIZATION");
+    }
+}
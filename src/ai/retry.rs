@@ -0,0 +1,152 @@
+use super::{AIError, ErrorKind};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Attempts per request when `max_attempts` is not set
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Seconds before a request is abandoned when `request_timeout` is not set
+pub const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 60;
+
+/// Wait before the first retry when `retry_backoff_ms` is not set
+pub const DEFAULT_RETRY_BACKOFF_MS: u64 = 500;
+
+/// Longest wait between two attempts of our own choosing
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Longest `Retry-After` worth waiting for in front of a commit; beyond it the
+/// request fails straight away
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// How often and how patiently a failed request is retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total, 1 to never retry
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled for each one after it
+    pub initial_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: Duration::from_millis(DEFAULT_RETRY_BACKOFF_MS),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// How long to wait after attempt number `attempt` failed with `error`, `None`
+    /// when it should not be retried. The endpoint's `Retry-After` is honoured;
+    /// otherwise the backoff doubles with each attempt, with jitter so that
+    /// clients failing together don't retry together.
+    pub fn delay(&self, attempt: u32, error: &AIError) -> Option<Duration> {
+        if attempt >= self.max_attempts || !error.is_transient() {
+            return None;
+        }
+        if let ErrorKind::Http {
+            retry_after: Some(retry_after),
+            ..
+        } = error.kind
+        {
+            return (retry_after <= MAX_RETRY_AFTER).then_some(retry_after);
+        }
+
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(MAX_BACKOFF);
+        // Somewhere between half and all of the backoff
+        let half = backoff / 2;
+        let jitter = half.mul_f64(random_fraction(attempt));
+        Some(half + jitter)
+    }
+}
+
+/// Parse a `Retry-After` header, given either in seconds or as an HTTP date
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        return (seconds >= 0.0 && seconds.is_finite()).then(|| Duration::from_secs_f64(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.signed_duration_since(chrono::Utc::now());
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// A number in `0.0..1.0` that differs from call to call
+fn random_fraction(seed: u32) -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(seed);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn http_error(status: u16, retry_after: Option<Duration>) -> AIError {
+        AIError::new("error").with_kind(ErrorKind::Http {
+            status,
+            retry_after,
+        })
+    }
+
+    #[test]
+    fn test_backoff_doubles_with_jitter() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(1000),
+        };
+        let error = http_error(503, None);
+        for (attempt, full) in [(1, 1000), (2, 2000), (3, 4000)] {
+            let delay = policy.delay(attempt, &error).unwrap();
+            assert!(delay >= Duration::from_millis(full / 2), "{:?}", delay);
+            assert!(delay <= Duration::from_millis(full), "{:?}", delay);
+        }
+        assert_eq!(policy.delay(5, &error), None);
+
+        let policy = RetryPolicy {
+            max_attempts: 20,
+            ..policy
+        };
+        assert!(policy.delay(15, &error).unwrap() <= MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_only_transient_errors_are_retried() {
+        let policy = RetryPolicy::default();
+        assert!(policy.delay(1, &http_error(429, None)).is_some());
+        assert!(policy.delay(1, &http_error(500, None)).is_some());
+        assert!(policy
+            .delay(1, &AIError::new("timed out").with_kind(ErrorKind::Timeout))
+            .is_some());
+        assert_eq!(policy.delay(1, &http_error(401, None)), None);
+        assert_eq!(policy.delay(1, &http_error(400, None)), None);
+        assert_eq!(policy.delay(1, &http_error(409, None)), None);
+        assert_eq!(policy.delay(1, &AIError::new("Invalid prompt")), None);
+    }
+
+    #[test]
+    fn test_retry_after_is_honoured() {
+        let policy = RetryPolicy::default();
+        assert_eq!(
+            policy.delay(1, &http_error(429, Some(Duration::from_secs(7)))),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(
+            policy.delay(1, &http_error(429, Some(Duration::from_secs(600)))),
+            None
+        );
+
+        assert_eq!(parse_retry_after("2"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_retry_after("0.5"), Some(Duration::from_millis(500)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
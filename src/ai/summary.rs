@@ -0,0 +1,56 @@
+use std::str::FromStr;
+
+/// Estimated diff tokens in each summary request
+pub const SUMMARY_CHUNK_TOKENS: usize = 4_000;
+
+/// Summary requests in flight at once when `summarise_concurrency` is not set
+pub const DEFAULT_SUMMARY_CONCURRENCY: usize = 4;
+
+/// Prompt for summarising one part of the diff, used when the prompts file has no
+/// `## Summary Prompt` section
+pub const DEFAULT_SUMMARY_PROMPT: &str =
+    "Summarise the following part of a git diff in at most three short sentences. \
+Name the files and describe what changed and, where the code makes it clear, why. \
+Reply with the summary only.
+
+<diff>
+{diff}
+</diff>";
+
+/// When to summarise the diff part by part before asking for the commit message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SummariseMode {
+    /// Always send the diff, cut down to the token budget if needed
+    #[default]
+    Never,
+    /// Summarise diffs over the token budget
+    Auto,
+    /// Summarise every diff
+    Always,
+}
+
+impl FromStr for SummariseMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim().to_lowercase().as_str() {
+            "never" => Ok(SummariseMode::Never),
+            "auto" => Ok(SummariseMode::Auto),
+            "always" => Ok(SummariseMode::Always),
+            other => Err(format!(
+                "Unknown summarise mode '{}'. Supported modes: never, auto, always",
+                other
+            )),
+        }
+    }
+}
+
+impl SummariseMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SummariseMode::Never => "never",
+            SummariseMode::Auto => "auto",
+            SummariseMode::Always => "always",
+        }
+    }
+}
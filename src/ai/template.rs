@@ -0,0 +1,543 @@
+use log::warn;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::BTreeMap;
+
+// `{name}`, `{.}`, `{#if name}`, `{#each name}`, `{#else}`, `{/if}`, `{/each}`
+static TAG_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\{(?:(#if|#each) +([a-z_][a-z0-9_]*)|(#else|/if|/each)|([a-z_][a-z0-9_]*|\.))\}")
+        .unwrap()
+});
+
+/// A value that can be substituted into a template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Text(String),
+    List(Vec<String>),
+}
+
+impl Value {
+    /// Empty text and empty lists are false in `{#if}`
+    fn is_truthy(&self) -> bool {
+        match self {
+            Value::Text(text) => !text.trim().is_empty(),
+            Value::List(items) => !items.is_empty(),
+        }
+    }
+
+    fn render(&self) -> String {
+        match self {
+            Value::Text(text) => text.clone(),
+            Value::List(items) => items.join("\n"),
+        }
+    }
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Self {
+        Value::Text(text)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Self {
+        Value::Text(text.to_string())
+    }
+}
+
+impl From<Vec<String>> for Value {
+    fn from(items: Vec<String>) -> Self {
+        Value::List(items)
+    }
+}
+
+impl From<Option<String>> for Value {
+    fn from(text: Option<String>) -> Self {
+        Value::Text(text.unwrap_or_default())
+    }
+}
+
+/// The variables available to a template
+pub type Variables = BTreeMap<String, Value>;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct TemplateError {
+    pub message: String,
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+fn error(message: String) -> TemplateError {
+    TemplateError { message }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Text(String),
+    Var { name: String, line: usize },
+    If { name: String, line: usize },
+    Each { name: String, line: usize },
+    Else { line: usize },
+    EndIf { line: usize },
+    EndEach { line: usize },
+}
+
+impl Token {
+    fn is_block(&self) -> bool {
+        !matches!(self, Token::Text(_) | Token::Var { .. })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Var {
+        name: String,
+        line: usize,
+    },
+    If {
+        name: String,
+        line: usize,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Each {
+        name: String,
+        line: usize,
+        body: Vec<Node>,
+    },
+}
+
+/// A prompt template.
+///
+/// `{name}` inserts a variable (lists one item per line), `{#if name}...{#else}...{/if}`
+/// tests that a variable is not empty and `{#each name}...{.}...{/each}` repeats for every
+/// item of a list. `{{` is a literal `{`; braces around anything else, including a `{name}`
+/// that is not a variable such as `{type}({scope}): {description}`, are left as they are.
+/// Block tags on a line of their own do not leave an empty line behind.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let mut tokens = tokenize(source).into_iter().peekable();
+        let (nodes, end) = parse_nodes(&mut tokens)?;
+        match end {
+            None => Ok(Template { nodes }),
+            Some(token) => Err(unexpected(&token)),
+        }
+    }
+
+    /// Check every variable tested by `{#if}` or looped over by `{#each}` is one of `known`,
+    /// so that typos are reported even inside branches that are not taken
+    pub fn check_variables(&self, known: &[&str]) -> Result<(), TemplateError> {
+        check_nodes(&self.nodes, known, false)
+    }
+
+    /// The `{name}` tags that are not one of `known` with their line, which are kept as
+    /// literal text
+    pub fn unknown_variables(&self, known: &[&str]) -> Vec<(String, usize)> {
+        let mut unknown = Vec::new();
+        collect_unknown(&self.nodes, known, &mut unknown);
+        unknown
+    }
+
+    pub fn render(&self, variables: &Variables) -> Result<String, TemplateError> {
+        let known: Vec<&str> = variables.keys().map(String::as_str).collect();
+        self.check_variables(&known)?;
+        for (name, line) in self.unknown_variables(&known) {
+            warn!(
+                "{{{}}} on line {} is not a template variable, it is kept as written",
+                name, line
+            );
+        }
+
+        let mut output = String::new();
+        render_nodes(&self.nodes, variables, None, &mut output);
+        Ok(output)
+    }
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut line = 1;
+    let mut rest = source;
+
+    while let Some(index) = rest.find('{') {
+        text.push_str(&rest[..index]);
+        line += rest[..index].matches('\n').count();
+        rest = &rest[index..];
+
+        if rest.starts_with("{{") {
+            text.push('{');
+            rest = &rest[2..];
+            continue;
+        }
+
+        let Some(captures) = TAG_REGEX.captures(rest) else {
+            text.push('{');
+            rest = &rest[1..];
+            continue;
+        };
+
+        let token = if let Some(name) = captures.get(4) {
+            Token::Var {
+                name: name.as_str().to_string(),
+                line,
+            }
+        } else if let Some(keyword) = captures.get(3) {
+            match keyword.as_str() {
+                "#else" => Token::Else { line },
+                "/if" => Token::EndIf { line },
+                _ => Token::EndEach { line },
+            }
+        } else {
+            let name = captures[2].to_string();
+            match &captures[1] {
+                "#if" => Token::If { name, line },
+                _ => Token::Each { name, line },
+            }
+        };
+
+        if !text.is_empty() {
+            tokens.push(Token::Text(std::mem::take(&mut text)));
+        }
+        tokens.push(token);
+        rest = &rest[captures[0].len()..];
+    }
+
+    text.push_str(rest);
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+
+    strip_standalone_lines(tokens)
+}
+
+/// Remove the indentation and line break around block tags that sit on a line of their own
+fn strip_standalone_lines(mut tokens: Vec<Token>) -> Vec<Token> {
+    let mut stripped = vec![false; tokens.len()];
+
+    for index in 0..tokens.len() {
+        if !tokens[index].is_block()
+            || !starts_line(&tokens[..index], &stripped)
+            || !ends_line(&tokens[index + 1..])
+        {
+            continue;
+        }
+        stripped[index] = true;
+
+        if let Some(Token::Text(text)) = index.checked_sub(1).map(|i| &mut tokens[i]) {
+            text.truncate(text.rfind('\n').map_or(0, |i| i + 1));
+        }
+        if let Some(Token::Text(text)) = tokens.get_mut(index + 1) {
+            *text = match text.find('\n') {
+                Some(i) => text[i + 1..].to_string(),
+                None => String::new(),
+            };
+        }
+    }
+
+    tokens
+}
+
+/// Whether only whitespace separates the end of `before` from the start of a line
+fn starts_line(before: &[Token], stripped: &[bool]) -> bool {
+    for (index, token) in before.iter().enumerate().rev() {
+        match token {
+            Token::Text(text) => match text.rfind('\n') {
+                Some(i) => return text[i + 1..].trim().is_empty(),
+                None if text.trim().is_empty() => continue,
+                None => return false,
+            },
+            // a removed tag line leaves the next token at the start of a line
+            _ => return stripped[index],
+        }
+    }
+    true
+}
+
+/// Whether only whitespace separates the start of `after` from the end of its line
+fn ends_line(after: &[Token]) -> bool {
+    for token in after {
+        match token {
+            Token::Text(text) => match text.find('\n') {
+                Some(i) => return text[..i].trim().is_empty(),
+                None if text.trim().is_empty() => continue,
+                None => return false,
+            },
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// Parse nodes up to the end of input or a closing tag, which is returned
+fn parse_nodes(
+    tokens: &mut std::iter::Peekable<std::vec::IntoIter<Token>>,
+) -> Result<(Vec<Node>, Option<Token>), TemplateError> {
+    let mut nodes = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Text(text) => nodes.push(Node::Text(text)),
+            Token::Var { name, line } => nodes.push(Node::Var { name, line }),
+            Token::If { name, line } => {
+                let (then, end) = parse_nodes(tokens)?;
+                let otherwise = match end {
+                    Some(Token::EndIf { .. }) => Vec::new(),
+                    Some(Token::Else { .. }) => match parse_nodes(tokens)? {
+                        (otherwise, Some(Token::EndIf { .. })) => otherwise,
+                        (_, Some(token)) => return Err(unexpected(&token)),
+                        (_, None) => return Err(unclosed("#if", line)),
+                    },
+                    Some(token) => return Err(unexpected(&token)),
+                    None => return Err(unclosed("#if", line)),
+                };
+                nodes.push(Node::If {
+                    name,
+                    line,
+                    then,
+                    otherwise,
+                });
+            }
+            Token::Each { name, line } => match parse_nodes(tokens)? {
+                (body, Some(Token::EndEach { .. })) => nodes.push(Node::Each { name, line, body }),
+                (_, Some(token)) => return Err(unexpected(&token)),
+                (_, None) => return Err(unclosed("#each", line)),
+            },
+            closing => return Ok((nodes, Some(closing))),
+        }
+    }
+
+    Ok((nodes, None))
+}
+
+fn unexpected(token: &Token) -> TemplateError {
+    let (tag, line) = match token {
+        Token::Else { line } => ("{#else}", line),
+        Token::EndIf { line } => ("{/if}", line),
+        Token::EndEach { line } => ("{/each}", line),
+        _ => ("tag", &0),
+    };
+    error(format!("Unexpected {} on line {}", tag, line))
+}
+
+fn unclosed(tag: &str, line: usize) -> TemplateError {
+    error(format!("{{{} ...}} on line {} is never closed", tag, line))
+}
+
+fn check_nodes(nodes: &[Node], known: &[&str], in_each: bool) -> Result<(), TemplateError> {
+    let check = |name: &str, line: usize| {
+        if name == "." && in_each || known.contains(&name) {
+            return Ok(());
+        }
+        if name == "." {
+            return Err(error(format!(
+                "{{.}} on line {} is only available inside {{#each}}",
+                line
+            )));
+        }
+        Err(error(format!(
+            "Unknown variable {{{}}} on line {}. Available variables: {}",
+            name,
+            line,
+            known.join(", ")
+        )))
+    };
+
+    for node in nodes {
+        match node {
+            Node::Text(_) => {}
+            Node::Var { name, line } if name == "." => check(name, *line)?,
+            Node::Var { .. } => {}
+            Node::If {
+                name,
+                line,
+                then,
+                otherwise,
+            } => {
+                check(name, *line)?;
+                check_nodes(then, known, in_each)?;
+                check_nodes(otherwise, known, in_each)?;
+            }
+            Node::Each { name, line, body } => {
+                check(name, *line)?;
+                check_nodes(body, known, true)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn collect_unknown(nodes: &[Node], known: &[&str], unknown: &mut Vec<(String, usize)>) {
+    for node in nodes {
+        match node {
+            Node::Text(_) => {}
+            Node::Var { name, line } => {
+                if name != "." && !known.contains(&name.as_str()) {
+                    unknown.push((name.clone(), *line));
+                }
+            }
+            Node::If {
+                then, otherwise, ..
+            } => {
+                collect_unknown(then, known, unknown);
+                collect_unknown(otherwise, known, unknown);
+            }
+            Node::Each { body, .. } => collect_unknown(body, known, unknown),
+        }
+    }
+}
+
+fn render_nodes(nodes: &[Node], variables: &Variables, item: Option<&str>, output: &mut String) {
+    let lookup = |name: &str| -> Value {
+        match (name, item) {
+            (".", Some(item)) => Value::Text(item.to_string()),
+            _ => variables
+                .get(name)
+                .cloned()
+                .unwrap_or(Value::Text(String::new())),
+        }
+    };
+
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Var { name, .. } if name != "." && !variables.contains_key(name) => {
+                output.push_str(&format!("{{{}}}", name))
+            }
+            Node::Var { name, .. } => output.push_str(&lookup(name).render()),
+            Node::If {
+                name,
+                then,
+                otherwise,
+                ..
+            } => {
+                let branch = if lookup(name).is_truthy() {
+                    then
+                } else {
+                    otherwise
+                };
+                render_nodes(branch, variables, item, output);
+            }
+            Node::Each { name, body, .. } => {
+                let items = match lookup(name) {
+                    Value::List(items) => items,
+                    Value::Text(text) if text.is_empty() => Vec::new(),
+                    Value::Text(text) => vec![text],
+                };
+                for each in &items {
+                    render_nodes(body, variables, Some(each), output);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> Variables {
+        let mut variables = Variables::new();
+        variables.insert("branch".to_string(), "feature/login".into());
+        variables.insert("issue_id".to_string(), "".into());
+        variables.insert(
+            "staged_files".to_string(),
+            vec!["src/a.rs".to_string(), "src/b.rs".to_string()].into(),
+        );
+        variables
+    }
+
+    fn render(source: &str) -> Result<String, TemplateError> {
+        Template::parse(source)?.render(&variables())
+    }
+
+    #[test]
+    fn test_variables() {
+        assert_eq!(render("Branch: {branch}").unwrap(), "Branch: feature/login");
+        assert_eq!(
+            render("Files:\n{staged_files}\n").unwrap(),
+            "Files:\nsrc/a.rs\nsrc/b.rs\n"
+        );
+    }
+
+    #[test]
+    fn test_conditionals() {
+        assert_eq!(
+            render("{#if issue_id}Refs {issue_id}{#else}No issue{/if}").unwrap(),
+            "No issue"
+        );
+        assert_eq!(
+            render("{#if branch}on {branch}{/if}.").unwrap(),
+            "on feature/login."
+        );
+    }
+
+    #[test]
+    fn test_loops_and_standalone_tags() {
+        let source = "Files:\n{#each staged_files}\n  - {.}\n{/each}\nEnd\n";
+        assert_eq!(
+            render(source).unwrap(),
+            "Files:\n  - src/a.rs\n  - src/b.rs\nEnd\n"
+        );
+
+        let source = "{#if branch}\n{#each staged_files}\n{.}\n{/each}\n{/if}\ndone";
+        assert_eq!(render(source).unwrap(), "src/a.rs\nsrc/b.rs\ndone");
+    }
+
+    #[test]
+    fn test_literal_braces() {
+        assert_eq!(
+            render("{\"type\": \"feat\"} {{branch} { x }").unwrap(),
+            "{\"type\": \"feat\"} {branch} { x }"
+        );
+    }
+
+    #[test]
+    fn test_unknown_variables_are_kept() {
+        let source = "Use {type}({scope}): {description} on {branch}";
+        assert_eq!(
+            render(source).unwrap(),
+            "Use {type}({scope}): {description} on feature/login"
+        );
+
+        let template =
+            Template::parse("line one\n{brnch}\n{#each staged_files}{.}{/each}").unwrap();
+        assert_eq!(
+            template.unknown_variables(&["branch", "staged_files"]),
+            [("brnch".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = render("line one\n{#if brnch}x{/if}")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Unknown variable {brnch} on line 2"));
+        assert!(error.contains("branch"));
+
+        // unknown variables are reported even in branches that are not taken
+        assert!(render("{#if issue_id}{#each fles}{.}{/each}{/if}").is_err());
+        assert!(render("{#if branch}open")
+            .unwrap_err()
+            .message
+            .contains("never closed"));
+        assert!(render("{/each}")
+            .unwrap_err()
+            .message
+            .contains("Unexpected {/each}"));
+        assert!(render("{.}").is_err());
+    }
+}
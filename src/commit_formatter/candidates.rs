@@ -0,0 +1,65 @@
+/// Merge duplicate candidate messages and order them best first.
+///
+/// Candidates are compared ignoring case and whitespace. A message the model
+/// produced more than once ranks higher; ties keep the order they arrived in.
+pub fn rank_candidates(messages: Vec<String>) -> Vec<String> {
+    let mut ranked: Vec<(String, String, usize)> = Vec::new();
+
+    for message in messages {
+        if message.trim().is_empty() {
+            continue;
+        }
+
+        let key = normalise(&message);
+        match ranked.iter_mut().find(|(existing, _, _)| *existing == key) {
+            Some((_, _, count)) => *count += 1,
+            None => ranked.push((key, message, 1)),
+        }
+    }
+
+    // Stable sort keeps arrival order between candidates seen equally often
+    ranked.sort_by_key(|(_, _, count)| std::cmp::Reverse(*count));
+    ranked.into_iter().map(|(_, message, _)| message).collect()
+}
+
+fn normalise(message: &str) -> String {
+    message
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_candidates_removes_duplicates() {
+        let ranked = rank_candidates(vec![
+            "feat: add login".to_string(),
+            "fix: handle timeout".to_string(),
+            "Feat:  add login".to_string(),
+            "".to_string(),
+        ]);
+        assert_eq!(ranked, ["feat: add login", "fix: handle timeout"]);
+    }
+
+    #[test]
+    fn test_rank_candidates_prefers_repeated_messages() {
+        let ranked = rank_candidates(vec![
+            "docs: update readme".to_string(),
+            "fix: handle timeout".to_string(),
+            "fix: handle timeout".to_string(),
+            "chore: bump version".to_string(),
+        ]);
+        assert_eq!(
+            ranked,
+            [
+                "fix: handle timeout",
+                "docs: update readme",
+                "chore: bump version"
+            ]
+        );
+    }
+}
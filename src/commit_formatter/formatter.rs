@@ -1,6 +1,11 @@
+use super::lint::{LintReport, LintRules, Linter};
+use super::repair::repair;
 use super::types::CommitType;
+use super::wrap::{truncate_subject, wrap_body};
+use log::warn;
 use once_cell::sync::Lazy;
 use regex::Regex;
+use std::str::FromStr;
 
 // Compile regex patterns once for better performance
 static CODE_BLOCK_REGEX: Lazy<Regex> = Lazy::new(|| {
@@ -14,13 +19,76 @@ static SIMPLE_CODE_BLOCK_REGEX: Lazy<Regex> =
 static INLINE_KEYWORD_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^(?:shell|sh|bash|plaintext|text|markdown|md)\s+").unwrap());
 
+/// What to do with a subject line longer than the limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SubjectStrategy {
+    /// Keep the subject and log a warning
+    #[default]
+    Warn,
+    /// Cut the subject at the last word that fits
+    Truncate,
+}
+
+impl FromStr for SubjectStrategy {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim().to_lowercase().as_str() {
+            "warn" => Ok(SubjectStrategy::Warn),
+            "truncate" => Ok(SubjectStrategy::Truncate),
+            other => Err(format!(
+                "Unknown subject strategy '{}'. Supported strategies: warn, truncate",
+                other
+            )),
+        }
+    }
+}
+
+impl SubjectStrategy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SubjectStrategy::Warn => "warn",
+            SubjectStrategy::Truncate => "truncate",
+        }
+    }
+}
+
+/// Layout applied by `CommitFormatter::format`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Width body lines are wrapped to; 0 disables wrapping
+    pub body_width: usize,
+    /// Maximum length of the subject line
+    pub max_subject_length: usize,
+    pub subject_strategy: SubjectStrategy,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            body_width: 72,
+            max_subject_length: 72,
+            subject_strategy: SubjectStrategy::Warn,
+        }
+    }
+}
+
 pub struct CommitFormatter {
     raw_message: String,
+    options: FormatOptions,
 }
 
 impl CommitFormatter {
     pub fn new(raw_message: String) -> Self {
-        CommitFormatter { raw_message }
+        CommitFormatter {
+            raw_message,
+            options: FormatOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: FormatOptions) -> Self {
+        self.options = options;
+        self
     }
 
     pub fn format(&self) -> CommitType {
@@ -50,13 +118,69 @@ impl CommitFormatter {
             .replace(&cleaned_message, "")
             .to_string();
 
+        // Wrap the body while code spans are still marked, so they stay on one line
+        cleaned_message = self.wrap(cleaned_message.trim());
+
         // Remove remaining backticks (for backward compatibility)
         cleaned_message = cleaned_message.replace("`", "");
 
         // Trim whitespace
         cleaned_message = cleaned_message.trim().to_string();
 
-        CommitType::new(cleaned_message)
+        CommitType::new(self.enforce_subject_length(cleaned_message))
+    }
+
+    /// Wrap everything after the first line to the configured body width
+    fn wrap(&self, message: &str) -> String {
+        match message.split_once('\n') {
+            Some((subject, body)) => {
+                format!("{}\n{}", subject, wrap_body(body, self.options.body_width))
+            }
+            None => message.to_string(),
+        }
+    }
+
+    fn enforce_subject_length(&self, message: String) -> String {
+        let (subject, rest) = match message.split_once('\n') {
+            Some((subject, rest)) => (subject, Some(rest)),
+            None => (message.as_str(), None),
+        };
+        let length = subject.chars().count();
+        if length <= self.options.max_subject_length {
+            return message;
+        }
+
+        match self.options.subject_strategy {
+            SubjectStrategy::Warn => {
+                warn!(
+                    "Subject line is {} characters, longer than the limit of {}",
+                    length, self.options.max_subject_length
+                );
+                message
+            }
+            SubjectStrategy::Truncate => {
+                let subject = truncate_subject(subject, self.options.max_subject_length);
+                warn!(
+                    "Subject line was {} characters, truncated to: {}",
+                    length, subject
+                );
+                match rest {
+                    Some(rest) => format!("{}\n{}", subject, rest),
+                    None => subject,
+                }
+            }
+        }
+    }
+
+    /// Format the message and fix mechanical problems such as labels, type case,
+    /// a trailing period and unwrapped body lines
+    pub fn format_repaired(&self, rules: &LintRules) -> CommitType {
+        CommitType::new(repair(self.format().as_str(), rules))
+    }
+
+    /// Format the message and check the result against the lint rules
+    pub fn lint(&self, rules: &LintRules) -> LintReport {
+        Linter::new(rules.clone()).lint(self.format().as_str())
     }
 }
 
@@ -264,6 +388,94 @@ Implemented rate limiting to prevent API abuse"
         );
     }
 
+    #[test]
+    fn test_lint_formatted_message() {
+        let formatter =
+            CommitFormatter::new("<commit_message>feat(auth): add login</commit_message>".into());
+        assert!(formatter.lint(&LintRules::default()).is_clean());
+
+        let formatter = CommitFormatter::new("```feature: Added login.```".into());
+        let report = formatter.lint(&LintRules::default());
+        assert!(report.has_errors());
+    }
+
+    #[test]
+    fn test_format_repaired_fixes_lint_errors() {
+        let formatter = CommitFormatter::new(
+            "```plaintext\nSubject: Fix(api): Handle null response.\n\nBody:\nThe response handler now checks for null so that empty payloads no longer crash the client.\n```"
+                .to_string(),
+        );
+        assert!(formatter.lint(&LintRules::default()).has_errors());
+
+        let repaired = formatter.format_repaired(&LintRules::default());
+        assert!(repaired
+            .as_str()
+            .starts_with("fix(api): Handle null response\n\nThe response handler"));
+        assert!(!Linter::new(LintRules::default())
+            .lint(repaired.as_str())
+            .has_errors());
+    }
+
+    #[test]
+    fn test_format_wraps_body() {
+        let message = "<commit_message>\nfix(api): Handle null response\n\nThe response handler now checks for `null payloads` before decoding, see https://example.com/issues/12345/comments for the report.\n\n- Added null checks in the response handler and improved the error messages\n</commit_message>";
+        let formatted = format!("{}", CommitFormatter::new(message.to_string()).format());
+
+        assert!(formatted
+            .lines()
+            .filter(|line| !line.starts_with('-'))
+            .all(|line| line.chars().count() <= 72));
+        assert!(formatted.contains("null payloads"));
+        assert!(formatted.contains("https://example.com/issues/12345/comments"));
+        assert!(formatted.ends_with(
+            "\n- Added null checks in the response handler and improved the error messages"
+        ));
+    }
+
+    #[test]
+    fn test_format_with_custom_width() {
+        let message = "docs: Explain setup\n\nDescribe every environment variable the tool reads.";
+        let options = FormatOptions {
+            body_width: 20,
+            ..FormatOptions::default()
+        };
+        let formatted = CommitFormatter::new(message.to_string())
+            .with_options(options)
+            .format();
+        assert!(formatted
+            .as_str()
+            .lines()
+            .all(|line| line.chars().count() <= 20));
+    }
+
+    #[test]
+    fn test_subject_length_strategies() {
+        let message =
+            "feat(api): add pagination, filtering and sorting to the list endpoints\n\nBody";
+        let options = FormatOptions {
+            max_subject_length: 40,
+            ..FormatOptions::default()
+        };
+
+        let warned = CommitFormatter::new(message.to_string())
+            .with_options(options.clone())
+            .format();
+        assert_eq!(warned.as_str(), message);
+
+        let truncated = CommitFormatter::new(message.to_string())
+            .with_options(FormatOptions {
+                subject_strategy: SubjectStrategy::Truncate,
+                ..options
+            })
+            .format();
+        assert_eq!(
+            truncated.as_str(),
+            "feat(api): add pagination, filtering and\n\nBody"
+        );
+        assert_eq!("truncate".parse(), Ok(SubjectStrategy::Truncate));
+        assert!("cut".parse::<SubjectStrategy>().is_err());
+    }
+
     #[test]
     fn test_real_llm_examples_from_issue() {
         // Example 1 from GitHub issue #18
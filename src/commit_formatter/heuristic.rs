@@ -0,0 +1,246 @@
+use super::lint::LintRules;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Files listed in the body of a heuristic message before the rest are counted
+const MAX_LISTED_FILES: usize = 20;
+
+/// Directories too generic to make a useful scope
+const GENERIC_DIRECTORIES: &[&str] = &["src", "lib", "app", "pkg", "crates", "packages"];
+
+/// What the `prepare-commit-msg` hook does when no message can be generated, e.g.
+/// because the endpoint is down or no API key is set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HookFailure {
+    /// Fail the hook, which aborts the commit
+    Block,
+    /// Print a warning and leave the message to the user
+    Warn,
+    /// Print a warning and suggest a message built from the staged file names
+    #[default]
+    Heuristic,
+}
+
+impl FromStr for HookFailure {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim().to_lowercase().as_str() {
+            "block" => Ok(HookFailure::Block),
+            "warn" => Ok(HookFailure::Warn),
+            "heuristic" => Ok(HookFailure::Heuristic),
+            other => Err(format!(
+                "Unknown hook failure policy '{}'. Supported policies: block, warn, heuristic",
+                other
+            )),
+        }
+    }
+}
+
+impl HookFailure {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookFailure::Block => "block",
+            HookFailure::Warn => "warn",
+            HookFailure::Heuristic => "heuristic",
+        }
+    }
+}
+
+/// A plain commit message for the staged `files`, used when the model can't be reached.
+/// The type and scope are guessed from the paths and kept within `rules`, so the message
+/// passes the `commit-msg` hook.
+pub fn heuristic_message(files: &[&str], rules: &LintRules) -> String {
+    let files: Vec<&str> = files
+        .iter()
+        .map(|file| file.trim())
+        .filter(|file| !file.is_empty())
+        .collect();
+
+    let commit_type = commit_type(&files, rules);
+    let directory = common_directory(&files);
+    let scope = directory
+        .and_then(|directory| directory.rsplit('/').next())
+        .filter(|scope| !GENERIC_DIRECTORIES.contains(scope))
+        .filter(|scope| rules.scopes.is_empty() || rules.scopes.iter().any(|s| s == scope));
+
+    let header = |subject: &str| match scope {
+        Some(scope) => format!("{}({}): {}", commit_type, scope, subject),
+        None => format!("{}: {}", commit_type, subject),
+    };
+    let counted = match files.len() {
+        1 => "update 1 file".to_string(),
+        count => format!("update {} files", count),
+    };
+    let subject = match (files.as_slice(), directory) {
+        ([], _) => "update staged changes".to_string(),
+        ([file], _) => format!("update {}", file_name(file)),
+        (_, Some(directory)) => format!("update {} files in {}", files.len(), directory),
+        _ => counted.clone(),
+    };
+    let mut message = header(&subject);
+    if message.chars().count() > rules.max_subject_length {
+        message = header(&counted);
+    }
+
+    if files.len() > 1 {
+        message.push('\n');
+        for file in files.iter().take(MAX_LISTED_FILES) {
+            message.push_str(&format!("\n- {}", file));
+        }
+        if files.len() > MAX_LISTED_FILES {
+            message.push_str(&format!("\n- and {} more", files.len() - MAX_LISTED_FILES));
+        }
+    }
+    message
+}
+
+/// The type all files agree on, `chore` when they don't
+fn commit_type(files: &[&str], rules: &LintRules) -> String {
+    let mut kinds = files.iter().map(|file| file_kind(file));
+    let kind = match kinds.next() {
+        Some(first) if kinds.all(|kind| kind == first) => first,
+        _ => "chore",
+    };
+    let allowed =
+        |commit_type: &str| rules.types.is_empty() || rules.types.iter().any(|t| t == commit_type);
+    [kind, "chore"]
+        .into_iter()
+        .find(|commit_type| allowed(commit_type))
+        .map(str::to_string)
+        .or_else(|| rules.types.first().cloned())
+        .unwrap_or_else(|| "chore".to_string())
+}
+
+/// The commit type a change to `path` alone would get
+fn file_kind(path: &str) -> &'static str {
+    let lower = path.to_lowercase();
+    let name = file_name(&lower);
+    let directories: Vec<&str> = lower.split('/').rev().skip(1).collect();
+    let extension = Path::new(name).extension().and_then(|ext| ext.to_str());
+
+    if lower.starts_with(".github/workflows/")
+        || lower.starts_with(".circleci/")
+        || name == ".gitlab-ci.yml"
+        || name == "jenkinsfile"
+    {
+        "ci"
+    } else if directories
+        .iter()
+        .any(|dir| matches!(*dir, "test" | "tests" | "__tests__" | "spec"))
+        || name.starts_with("test_")
+        || ["_test.", ".test.", "_spec.", ".spec."]
+            .iter()
+            .any(|marker| name.contains(marker))
+    {
+        "test"
+    } else if directories
+        .iter()
+        .any(|dir| *dir == "docs" || *dir == "doc")
+        || matches!(extension, Some("md" | "rst" | "adoc"))
+        || name.starts_with("license")
+    {
+        "docs"
+    } else if matches!(
+        name,
+        "cargo.toml"
+            | "cargo.lock"
+            | "build.rs"
+            | "package.json"
+            | "package-lock.json"
+            | "yarn.lock"
+            | "pnpm-lock.yaml"
+            | "go.mod"
+            | "go.sum"
+            | "pyproject.toml"
+            | "makefile"
+            | "dockerfile"
+    ) {
+        "build"
+    } else {
+        "chore"
+    }
+}
+
+/// The deepest directory holding all of `files`, `None` at the repository root
+fn common_directory<'a>(files: &[&'a str]) -> Option<&'a str> {
+    let first = files.first()?;
+    let mut common = first.rsplit_once('/')?.0;
+    for file in &files[1..] {
+        while !file.starts_with(&format!("{}/", common)) {
+            common = common.rsplit_once('/')?.0;
+        }
+    }
+    Some(common)
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit_formatter::Linter;
+
+    #[test]
+    fn test_heuristic_message() {
+        let rules = LintRules::default();
+        assert_eq!(
+            heuristic_message(&["src/git/filter.rs"], &rules),
+            "chore(git): update filter.rs"
+        );
+        assert_eq!(
+            heuristic_message(&["readme.md", "docs/setup.md"], &rules),
+            "docs: update 2 files\n\n- readme.md\n- docs/setup.md"
+        );
+        assert_eq!(
+            heuristic_message(&["src/ai/tests/retry.rs", "src/ai/tests/mock.rs"], &rules),
+            "test(tests): update 2 files in src/ai/tests\n\n- src/ai/tests/retry.rs\n- src/ai/tests/mock.rs"
+        );
+        assert_eq!(
+            heuristic_message(&["src/main.rs", "Cargo.toml"], &rules)
+                .lines()
+                .next(),
+            Some("chore: update 2 files")
+        );
+        assert_eq!(
+            heuristic_message(&[], &rules),
+            "chore: update staged changes"
+        );
+
+        let many: Vec<String> = (0..25).map(|i| format!("src/ui/view{}.rs", i)).collect();
+        let many: Vec<&str> = many.iter().map(String::as_str).collect();
+        let message = heuristic_message(&many, &rules);
+        assert!(message.starts_with("chore(ui): update 25 files in src/ui\n"));
+        assert!(message.ends_with("- src/ui/view19.rs\n- and 5 more"));
+        assert!(!Linter::new(rules.clone()).lint(&message).has_errors());
+    }
+
+    #[test]
+    fn test_heuristic_message_follows_rules() {
+        let rules = LintRules {
+            types: vec!["feat".to_string(), "fix".to_string()],
+            scopes: vec!["api".to_string()],
+            max_subject_length: 25,
+            ..LintRules::default()
+        };
+        assert_eq!(
+            heuristic_message(&["src/api/handlers/authentication.rs"], &rules),
+            "feat: update 1 file"
+        );
+        assert_eq!(
+            heuristic_message(&["src/api/routes.rs", "src/api/auth.rs"], &rules)
+                .lines()
+                .next(),
+            Some("feat(api): update 2 files")
+        );
+    }
+
+    #[test]
+    fn test_hook_failure_names() {
+        assert_eq!("Warn".parse::<HookFailure>().unwrap(), HookFailure::Warn);
+        assert_eq!(HookFailure::default().name(), "heuristic");
+        assert!("ignore".parse::<HookFailure>().is_err());
+    }
+}
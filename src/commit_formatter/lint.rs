@@ -0,0 +1,367 @@
+use super::types::{ConventionalCommit, ParseError};
+use std::fmt;
+
+/// Commit types suggested by the default prompt
+pub const DEFAULT_TYPES: &[&str] = &[
+    "feat", "fix", "chore", "docs", "style", "refactor", "perf", "test", "build", "ci", "revert",
+];
+
+/// Prefixes of messages git or tooling generates, which are not linted
+const GENERATED_PREFIXES: &[&str] = &["Merge ", "Revert \"", "fixup! ", "squash! ", "amend! "];
+
+/// Past tense / gerund / third person verbs that still read as imperative
+const IMPERATIVE_EXCEPTIONS: &[&str] = &[
+    "address", "bring", "bless", "embed", "feed", "need", "process", "proceed", "seed", "shred",
+    "speed", "string", "bump", "access", "pass", "compress", "express", "suppress", "ping",
+];
+
+/// The rules a commit message is checked against
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintRules {
+    /// Allowed commit types; empty allows any
+    pub types: Vec<String>,
+    /// Allowed scopes; empty allows any
+    pub scopes: Vec<String>,
+    /// Whether every commit needs a scope
+    pub require_scope: bool,
+    /// Maximum length of the first line
+    pub max_subject_length: usize,
+    /// Maximum length of body and footer lines; 0 disables the check
+    pub body_wrap_width: usize,
+    /// Warn when the subject does not start with an imperative verb
+    pub imperative_mood: bool,
+}
+
+impl Default for LintRules {
+    fn default() -> Self {
+        LintRules {
+            types: DEFAULT_TYPES.iter().map(|t| t.to_string()).collect(),
+            scopes: Vec::new(),
+            require_scope: false,
+            max_subject_length: 72,
+            body_wrap_width: 72,
+            imperative_mood: true,
+        }
+    }
+}
+
+/// How serious a violation is; only errors fail a lint run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single rule violation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintViolation {
+    /// Rule identifier, e.g. `type-enum`
+    pub rule: &'static str,
+    pub severity: Severity,
+    /// 1-based line number in the message
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for LintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{} [{}] line {}: {}",
+            level, self.rule, self.line, self.message
+        )
+    }
+}
+
+/// Result of linting one message
+#[derive(Debug, Clone, Default)]
+pub struct LintReport {
+    pub violations: Vec<LintViolation>,
+}
+
+impl LintReport {
+    pub fn has_errors(&self) -> bool {
+        self.violations
+            .iter()
+            .any(|violation| violation.severity == Severity::Error)
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    fn push(&mut self, rule: &'static str, severity: Severity, line: usize, message: String) {
+        self.violations.push(LintViolation {
+            rule,
+            severity,
+            line,
+            message,
+        });
+    }
+}
+
+/// Validates commit messages, whether written by the AI or by hand
+pub struct Linter {
+    rules: LintRules,
+}
+
+impl Linter {
+    pub fn new(rules: LintRules) -> Self {
+        Linter { rules }
+    }
+
+    pub fn rules(&self) -> &LintRules {
+        &self.rules
+    }
+
+    pub fn lint(&self, message: &str) -> LintReport {
+        let mut report = LintReport::default();
+        let message = message.trim_end();
+
+        if GENERATED_PREFIXES
+            .iter()
+            .any(|prefix| message.starts_with(prefix))
+        {
+            return report;
+        }
+
+        let lines: Vec<&str> = message.lines().collect();
+        let header = lines.first().copied().unwrap_or_default();
+
+        if header.chars().count() > self.rules.max_subject_length {
+            report.push(
+                "subject-max-length",
+                Severity::Error,
+                1,
+                format!(
+                    "subject line is {} characters, the maximum is {}",
+                    header.chars().count(),
+                    self.rules.max_subject_length
+                ),
+            );
+        }
+
+        if lines.len() > 1 && !lines[1].trim().is_empty() {
+            report.push(
+                "blank-line-after-subject",
+                Severity::Error,
+                2,
+                "the subject must be followed by a blank line".to_string(),
+            );
+        }
+
+        if self.rules.body_wrap_width > 0 {
+            for (index, line) in lines.iter().enumerate().skip(1) {
+                let length = line.chars().count();
+                // Long URLs cannot be wrapped, so lines containing one are exempt
+                if length > self.rules.body_wrap_width && !line.contains("://") {
+                    report.push(
+                        "body-max-line-length",
+                        Severity::Error,
+                        index + 1,
+                        format!(
+                            "line is {} characters, wrap at {}",
+                            length, self.rules.body_wrap_width
+                        ),
+                    );
+                }
+            }
+        }
+
+        match message.parse::<ConventionalCommit>() {
+            Ok(commit) => self.lint_commit(&commit, &mut report),
+            Err(e) => {
+                let rule = match e {
+                    ParseError::EmptySubject => "subject-empty",
+                    ParseError::EmptyScope => "scope-empty",
+                    ParseError::Empty | ParseError::InvalidHeader(_) => "header-format",
+                };
+                report.push(rule, Severity::Error, 1, e.to_string());
+            }
+        }
+
+        report
+    }
+
+    fn lint_commit(&self, commit: &ConventionalCommit, report: &mut LintReport) {
+        if !self.rules.types.is_empty() && !self.rules.types.contains(&commit.commit_type) {
+            report.push(
+                "type-enum",
+                Severity::Error,
+                1,
+                format!(
+                    "type '{}' is not allowed (allowed: {})",
+                    commit.commit_type,
+                    self.rules.types.join(", ")
+                ),
+            );
+        }
+
+        match &commit.scope {
+            None if self.rules.require_scope => report.push(
+                "scope-required",
+                Severity::Error,
+                1,
+                "a scope is required, e.g. 'feat(api): ...'".to_string(),
+            ),
+            Some(scope) if !self.rules.scopes.is_empty() && !self.rules.scopes.contains(scope) => {
+                report.push(
+                    "scope-enum",
+                    Severity::Error,
+                    1,
+                    format!(
+                        "scope '{}' is not allowed (allowed: {})",
+                        scope,
+                        self.rules.scopes.join(", ")
+                    ),
+                )
+            }
+            _ => {}
+        }
+
+        if commit.subject.ends_with('.') {
+            report.push(
+                "subject-full-stop",
+                Severity::Error,
+                1,
+                "the subject must not end with a period".to_string(),
+            );
+        }
+
+        if self.rules.imperative_mood {
+            if let Some(word) = non_imperative_verb(&commit.subject) {
+                report.push(
+                    "subject-imperative",
+                    Severity::Warning,
+                    1,
+                    format!(
+                        "'{}' does not look imperative, write the subject as a command (e.g. 'add' not 'added')",
+                        word
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// Heuristic: flag a first word that looks past tense, a gerund or third person
+fn non_imperative_verb(subject: &str) -> Option<&str> {
+    let word = subject.split_whitespace().next()?;
+    let lower = word.to_lowercase();
+
+    if lower.len() < 4 || IMPERATIVE_EXCEPTIONS.contains(&lower.as_str()) {
+        return None;
+    }
+
+    let looks_past = lower.ends_with("ed");
+    let looks_gerund = lower.ends_with("ing");
+    let looks_third_person =
+        lower.ends_with('s') && !lower.ends_with("ss") && !lower.ends_with("us");
+
+    if looks_past || looks_gerund || looks_third_person {
+        Some(word)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(violations: &LintReport) -> Vec<&'static str> {
+        violations.violations.iter().map(|v| v.rule).collect()
+    }
+
+    #[test]
+    fn test_valid_message_is_clean() {
+        let linter = Linter::new(LintRules::default());
+        let report = linter.lint("feat(auth): add login\n\nAllow users to sign in.\n\nRefs: #12");
+        assert!(report.is_clean(), "{:?}", report);
+    }
+
+    #[test]
+    fn test_disallowed_type_and_scope() {
+        let linter = Linter::new(LintRules {
+            scopes: vec!["api".to_string()],
+            ..LintRules::default()
+        });
+        let report = linter.lint("feature(ui): add button");
+        assert_eq!(rules(&report), ["type-enum", "scope-enum"]);
+        assert!(report.has_errors());
+    }
+
+    #[test]
+    fn test_required_scope() {
+        let linter = Linter::new(LintRules {
+            require_scope: true,
+            ..LintRules::default()
+        });
+        assert_eq!(rules(&linter.lint("fix: handle nulls")), ["scope-required"]);
+    }
+
+    #[test]
+    fn test_subject_rules() {
+        let linter = Linter::new(LintRules::default());
+        let long = format!("fix: {}", "a".repeat(80));
+        assert_eq!(rules(&linter.lint(&long)), ["subject-max-length"]);
+        assert_eq!(
+            rules(&linter.lint("fix: handle nulls.")),
+            ["subject-full-stop"]
+        );
+
+        let report = linter.lint("fix: Added null handling");
+        assert_eq!(rules(&report), ["subject-imperative"]);
+        assert!(!report.has_errors());
+    }
+
+    #[test]
+    fn test_body_rules() {
+        let linter = Linter::new(LintRules::default());
+        assert_eq!(
+            rules(&linter.lint("fix: handle nulls\nno blank line")),
+            ["blank-line-after-subject"]
+        );
+
+        let long_body = format!("fix: handle nulls\n\n{}", "word ".repeat(20));
+        let report = linter.lint(&long_body);
+        assert_eq!(rules(&report), ["body-max-line-length"]);
+        assert_eq!(report.violations[0].line, 3);
+
+        let url_body = format!(
+            "fix: handle nulls\n\nSee https://example.com/{}",
+            "x".repeat(80)
+        );
+        assert!(linter.lint(&url_body).is_clean());
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let linter = Linter::new(LintRules::default());
+        let report = linter.lint("Add login");
+        assert_eq!(rules(&report), ["header-format"]);
+        assert!(report.violations[0]
+            .to_string()
+            .starts_with("error [header-format] line 1"));
+    }
+
+    #[test]
+    fn test_generated_messages_are_skipped() {
+        let linter = Linter::new(LintRules::default());
+        assert!(linter.lint("Merge branch 'main' into feature").is_clean());
+        assert!(linter.lint("fixup! feat: add login").is_clean());
+    }
+
+    #[test]
+    fn test_imperative_heuristic() {
+        assert_eq!(non_imperative_verb("adds support"), Some("adds"));
+        assert_eq!(non_imperative_verb("updating docs"), Some("updating"));
+        assert_eq!(non_imperative_verb("add support"), None);
+        assert_eq!(non_imperative_verb("process queue"), None);
+        assert_eq!(non_imperative_verb("bump version"), None);
+    }
+}
@@ -1,4 +1,14 @@
+mod candidates;
 mod formatter;
+mod heuristic;
+mod lint;
+mod repair;
 mod types;
+mod wrap;
 
-pub use formatter::CommitFormatter;
+pub use candidates::rank_candidates;
+pub use formatter::{CommitFormatter, FormatOptions, SubjectStrategy};
+pub use heuristic::{heuristic_message, HookFailure};
+pub use lint::{LintReport, LintRules, LintViolation, Linter, Severity, DEFAULT_TYPES};
+pub use repair::repair;
+pub use types::{CommitType, ConventionalCommit, Footer, ParseError};
@@ -0,0 +1,139 @@
+use super::lint::LintRules;
+use super::types::ConventionalCommit;
+use super::wrap::wrap_body;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+// Section labels models like to add, e.g. "Subject: ..." or "**Body:**"
+static LABEL_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^\s*(?:\*\*)?(?:subject|title|summary|header|commit message|body|description|footer|footers)(?:\*\*)?\s*:(?:\*\*)?\s*(?P<rest>.*)$")
+        .unwrap()
+});
+
+/// Lines at the start of a message that may carry a section label: the header and
+/// the first line of the body
+const LABELLED_LINES: usize = 2;
+
+/// Fix mechanical format problems in a formatted commit message.
+///
+/// Drops "Subject:"/"Body:" style labels in front of the header and the first body
+/// line, lowercases the type, removes a trailing
+/// period from the subject, separates the subject from the body with a blank line
+/// and wraps body lines to `rules.body_wrap_width`. Problems that need rewording,
+/// such as a missing type or an overlong subject, are left for the linter to report.
+pub fn repair(message: &str, rules: &LintRules) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut labelled = 0;
+    for line in message.lines() {
+        let captures = LABEL_REGEX
+            .captures(line)
+            .filter(|_| labelled < LABELLED_LINES);
+        let line = match captures {
+            Some(captures) if captures["rest"].trim().is_empty() => continue,
+            Some(captures) => captures["rest"].trim_end().to_string(),
+            None => line.trim_end().to_string(),
+        };
+        if !line.trim().is_empty() {
+            labelled += 1;
+        }
+        lines.push(line);
+    }
+
+    while lines.first().is_some_and(|line| line.trim().is_empty()) {
+        lines.remove(0);
+    }
+    let Some(header) = lines.first().cloned() else {
+        return String::new();
+    };
+    lines[0] = repair_header(header.trim());
+
+    if lines.len() > 1 && !lines[1].trim().is_empty() {
+        lines.insert(1, String::new());
+    }
+
+    // Collapse runs of blank lines left behind by dropped labels
+    let mut collapsed: Vec<String> = Vec::new();
+    for line in lines {
+        if line.is_empty() && collapsed.last().is_some_and(|last| last.is_empty()) {
+            continue;
+        }
+        collapsed.push(line);
+    }
+
+    let header = collapsed.remove(0);
+    let body = wrap_body(collapsed.join("\n").trim(), rules.body_wrap_width);
+    if body.is_empty() {
+        header
+    } else {
+        format!("{}\n\n{}", header, body)
+    }
+}
+
+/// Lowercase the type and drop the trailing period, leaving unparseable headers alone
+fn repair_header(header: &str) -> String {
+    match header.parse::<ConventionalCommit>() {
+        Ok(mut commit) => {
+            commit.commit_type = commit.commit_type.to_lowercase();
+            commit.subject = commit.subject.trim_end_matches('.').trim_end().to_string();
+            if commit.subject.is_empty() {
+                return header.to_string();
+            }
+            commit.header()
+        }
+        Err(_) => header.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit_formatter::Linter;
+
+    #[test]
+    fn test_repair_labels_type_and_period() {
+        let message = "Subject: Feat(auth): add login.\nBody: Users can now sign in.";
+        assert_eq!(
+            repair(message, &LintRules::default()),
+            "feat(auth): add login\n\nUsers can now sign in."
+        );
+    }
+
+    #[test]
+    fn test_repair_issue_18_example() {
+        let message = "feat(formatter): Update test assertions for CommitFormatter\n\nBody:\n- Updated test assertions in CommitFormatter::format() to use format!(\"{}\", formatter.format()) instead of formatter.format().to_string().\n- Refactored CommitType struct to implement fmt::Display trait, replacing the previous to_string method.";
+        let repaired = repair(message, &LintRules::default());
+
+        assert!(!repaired.contains("Body:"));
+        assert!(repaired.starts_with(
+            "feat(formatter): Update test assertions for CommitFormatter\n\n- Updated"
+        ));
+        // The bullets are laid out by the model and kept as they are
+        assert!(repaired.ends_with("\n- Refactored CommitType struct to implement fmt::Display trait, replacing the previous to_string method."));
+        assert!(Linter::new(LintRules::default())
+            .lint(&repaired)
+            .violations
+            .iter()
+            .all(|violation| violation.rule == "body-max-line-length"));
+    }
+
+    #[test]
+    fn test_repair_keeps_labels_inside_the_body() {
+        let message = "Subject: fix(api): handle nulls\n\nBody: Null responses no longer crash.\n\nSummary: callers see an empty list.\nDescription: unchanged.";
+        assert_eq!(
+            repair(message, &LintRules::default()),
+            "fix(api): handle nulls\n\nNull responses no longer crash.\n\nSummary: callers see an empty list.\nDescription: unchanged."
+        );
+    }
+
+    #[test]
+    fn test_repair_leaves_unfixable_headers() {
+        let rules = LintRules::default();
+        assert_eq!(repair("Add login", &rules), "Add login");
+        assert_eq!(repair("", &rules), "");
+        assert_eq!(repair("fix: ...", &rules), "fix: ...");
+        assert_eq!(
+            repair("fix: handle nulls\n\nRefs: #12", &rules),
+            "fix: handle nulls\n\nRefs: #12"
+        );
+    }
+}
@@ -1,4 +1,17 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
 use std::fmt;
+use std::str::FromStr;
+
+static HEADER_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?P<type>[A-Za-z][A-Za-z0-9_-]*)(?:\((?P<scope>[^()\r\n]*)\))?(?P<breaking>!)?:\s*(?P<subject>.*)$")
+        .unwrap()
+});
+
+static FOOTER_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?P<token>BREAKING CHANGE|BREAKING-CHANGE|[A-Za-z][A-Za-z0-9-]*)(?P<separator>: | #)(?P<value>.*)$")
+        .unwrap()
+});
 
 #[derive(Debug, Clone)]
 pub struct CommitType {
@@ -9,6 +22,21 @@ impl CommitType {
     pub fn new(message: String) -> Self {
         CommitType { message }
     }
+
+    pub fn as_str(&self) -> &str {
+        &self.message
+    }
+
+    /// Parse the message into its Conventional Commit parts
+    pub fn parse(&self) -> Result<ConventionalCommit, ParseError> {
+        self.message.parse()
+    }
+}
+
+impl From<ConventionalCommit> for CommitType {
+    fn from(commit: ConventionalCommit) -> Self {
+        CommitType::new(commit.to_string())
+    }
 }
 
 impl fmt::Display for CommitType {
@@ -16,3 +44,340 @@ impl fmt::Display for CommitType {
         write!(f, "{}", self.message)
     }
 }
+
+/// Reasons a message is not a valid Conventional Commit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The message has no content
+    Empty,
+    /// The first line is not `type(scope)!: subject`
+    InvalidHeader(String),
+    /// The scope parentheses are present but empty
+    EmptyScope,
+    /// Nothing follows the `type: ` prefix
+    EmptySubject,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "commit message is empty"),
+            ParseError::InvalidHeader(header) => write!(
+                f,
+                "header '{}' does not match 'type(scope): subject'",
+                header
+            ),
+            ParseError::EmptyScope => {
+                write!(f, "scope must not be empty when parentheses are used")
+            }
+            ParseError::EmptySubject => write!(f, "subject must not be empty"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A footer/trailer such as `Refs: #123`, `Closes #42` or `BREAKING CHANGE: ...`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Footer {
+    pub token: String,
+    /// Either `": "` or `" #"`
+    pub separator: String,
+    pub value: String,
+}
+
+impl Footer {
+    pub fn new(token: impl Into<String>, value: impl Into<String>) -> Self {
+        Footer {
+            token: token.into(),
+            separator: ": ".to_string(),
+            value: value.into(),
+        }
+    }
+
+    pub fn is_breaking_change(&self) -> bool {
+        self.token == "BREAKING CHANGE" || self.token == "BREAKING-CHANGE"
+    }
+}
+
+impl fmt::Display for Footer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.token, self.separator, self.value)
+    }
+}
+
+/// A commit message split into its Conventional Commits parts.
+///
+/// `Display` writes the canonical form, so parsing it again yields the same value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConventionalCommit {
+    pub commit_type: String,
+    pub scope: Option<String>,
+    /// The `!` marker after the type/scope
+    pub breaking: bool,
+    pub subject: String,
+    pub body: Option<String>,
+    pub footers: Vec<Footer>,
+}
+
+impl ConventionalCommit {
+    pub fn new(commit_type: impl Into<String>, subject: impl Into<String>) -> Self {
+        ConventionalCommit {
+            commit_type: commit_type.into(),
+            scope: None,
+            breaking: false,
+            subject: subject.into(),
+            body: None,
+            footers: Vec::new(),
+        }
+    }
+
+    /// The first line: `type(scope)!: subject`
+    pub fn header(&self) -> String {
+        let mut header = self.commit_type.clone();
+        if let Some(scope) = &self.scope {
+            header.push_str(&format!("({})", scope));
+        }
+        if self.breaking {
+            header.push('!');
+        }
+        header.push_str(": ");
+        header.push_str(&self.subject);
+        header
+    }
+
+    /// True for a `!` marker or a `BREAKING CHANGE` footer
+    pub fn is_breaking_change(&self) -> bool {
+        self.breaking || self.footers.iter().any(Footer::is_breaking_change)
+    }
+
+    /// Value of the first footer with the given token
+    pub fn footer(&self, token: &str) -> Option<&str> {
+        self.footers
+            .iter()
+            .find(|footer| footer.token.eq_ignore_ascii_case(token))
+            .map(|footer| footer.value.as_str())
+    }
+}
+
+impl FromStr for ConventionalCommit {
+    type Err = ParseError;
+
+    fn from_str(message: &str) -> Result<Self, Self::Err> {
+        let message = message.trim();
+        if message.is_empty() {
+            return Err(ParseError::Empty);
+        }
+
+        let mut lines = message.lines();
+        let header = lines.next().unwrap_or_default().trim();
+        let captures = HEADER_REGEX
+            .captures(header)
+            .ok_or_else(|| ParseError::InvalidHeader(header.to_string()))?;
+
+        let scope = match captures.name("scope") {
+            Some(scope) if scope.as_str().trim().is_empty() => return Err(ParseError::EmptyScope),
+            Some(scope) => Some(scope.as_str().trim().to_string()),
+            None => None,
+        };
+        let subject = captures["subject"].trim().to_string();
+        if subject.is_empty() {
+            return Err(ParseError::EmptySubject);
+        }
+
+        let rest: Vec<&str> = lines.collect();
+        let (body, footers) = split_body_and_footers(&rest);
+
+        Ok(ConventionalCommit {
+            commit_type: captures["type"].to_string(),
+            scope,
+            breaking: captures.name("breaking").is_some(),
+            subject,
+            body,
+            footers,
+        })
+    }
+}
+
+impl fmt::Display for ConventionalCommit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.header())?;
+        if let Some(body) = &self.body {
+            write!(f, "\n\n{}", body)?;
+        }
+        if !self.footers.is_empty() {
+            write!(f, "\n\n")?;
+            for (index, footer) in self.footers.iter().enumerate() {
+                if index > 0 {
+                    writeln!(f)?;
+                }
+                write!(f, "{}", footer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Split the lines after the header into the body and the trailing footer block.
+///
+/// Footers are the last paragraph when it reads like git trailers: every line is
+/// `Token: value` or `Token #value`, with a token free of spaces apart from
+/// `BREAKING CHANGE`, or an indented continuation of the footer above it.
+fn split_body_and_footers(lines: &[&str]) -> (Option<String>, Vec<Footer>) {
+    let last_paragraph_start = lines
+        .iter()
+        .rposition(|line| line.trim().is_empty())
+        .map(|index| index + 1)
+        .unwrap_or(0);
+
+    let mut footers: Vec<Footer> = Vec::new();
+    let mut body_end = lines.len();
+
+    let footer_block = &lines[last_paragraph_start..];
+    let is_footer_block = footer_block
+        .first()
+        .is_some_and(|line| FOOTER_REGEX.is_match(line))
+        && footer_block[1..]
+            .iter()
+            .all(|line| FOOTER_REGEX.is_match(line) || line.starts_with(char::is_whitespace));
+    if is_footer_block {
+        body_end = last_paragraph_start;
+        for line in footer_block {
+            match FOOTER_REGEX.captures(line) {
+                Some(captures) => footers.push(Footer {
+                    token: captures["token"].to_string(),
+                    separator: captures["separator"].to_string(),
+                    value: captures["value"].trim_end().to_string(),
+                }),
+                None => {
+                    if let Some(footer) = footers.last_mut() {
+                        footer.value.push('\n');
+                        footer.value.push_str(line.trim_end());
+                    }
+                }
+            }
+        }
+    }
+
+    let body = lines[..body_end].join("\n").trim().to_string();
+    let body = if body.is_empty() { None } else { Some(body) };
+
+    (body, footers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_header_only() {
+        let commit: ConventionalCommit = "feat(auth): Add login functionality".parse().unwrap();
+        assert_eq!(commit.commit_type, "feat");
+        assert_eq!(commit.scope.as_deref(), Some("auth"));
+        assert!(!commit.breaking);
+        assert_eq!(commit.subject, "Add login functionality");
+        assert_eq!(commit.body, None);
+        assert!(commit.footers.is_empty());
+    }
+
+    #[test]
+    fn test_parse_breaking_marker_without_scope() {
+        let commit: ConventionalCommit = "refactor!: drop Node 14 support".parse().unwrap();
+        assert_eq!(commit.commit_type, "refactor");
+        assert_eq!(commit.scope, None);
+        assert!(commit.breaking);
+        assert!(commit.is_breaking_change());
+    }
+
+    #[test]
+    fn test_parse_body_and_footers() {
+        let message = "fix(api): Handle null response\n\n- Added null checks\n- Improved errors\n\nSecond paragraph.\n\nRefs: #123\nCloses #42\nBREAKING CHANGE: responses may now be empty\n  and callers must handle it";
+        let commit: ConventionalCommit = message.parse().unwrap();
+
+        assert_eq!(
+            commit.body.as_deref(),
+            Some("- Added null checks\n- Improved errors\n\nSecond paragraph.")
+        );
+        assert_eq!(commit.footers.len(), 3);
+        assert_eq!(commit.footer("refs"), Some("#123"));
+        assert_eq!(commit.footers[1].separator, " #");
+        assert_eq!(commit.footers[1].value, "42");
+        assert!(commit.footers[2].is_breaking_change());
+        assert!(commit.footers[2].value.ends_with("callers must handle it"));
+        assert!(commit.is_breaking_change());
+    }
+
+    #[test]
+    fn test_body_paragraph_that_looks_like_a_footer() {
+        let message = "feat(api): Add pagination\n\nNote: this changes the response shape\nfor every list endpoint.";
+        let commit: ConventionalCommit = message.parse().unwrap();
+        assert_eq!(
+            commit.body.as_deref(),
+            Some("Note: this changes the response shape\nfor every list endpoint.")
+        );
+        assert!(commit.footers.is_empty());
+
+        let message = "fix: Retry uploads\n\nWorks around flaky storage.\n\nRefs: #12\nSee the incident report for details.";
+        let commit: ConventionalCommit = message.parse().unwrap();
+        assert!(commit.footers.is_empty());
+        assert!(commit
+            .body
+            .unwrap()
+            .ends_with("See the incident report for details."));
+    }
+
+    #[test]
+    fn test_round_trip_display() {
+        let messages = [
+            "feat(auth): Add login functionality",
+            "chore!: bump MSRV",
+            "docs: update readme\n\nExplain the new flags.",
+            "fix(api)!: change error type\n\nBody text\nspanning lines\n\nReviewed-by: Jane\nRefs #7",
+        ];
+        for message in messages {
+            let commit: ConventionalCommit = message.parse().unwrap();
+            assert_eq!(commit.to_string(), message);
+            assert_eq!(
+                commit.to_string().parse::<ConventionalCommit>().unwrap(),
+                commit
+            );
+        }
+    }
+
+    #[test]
+    fn test_rewrite_parts() {
+        let mut commit: ConventionalCommit = "Feat(Core): add thing".parse().unwrap();
+        commit.commit_type = commit.commit_type.to_lowercase();
+        commit.scope = None;
+        commit.footers.push(Footer::new("Refs", "#9"));
+        assert_eq!(commit.to_string(), "feat: add thing\n\nRefs: #9");
+
+        let commit_type = CommitType::from(commit);
+        assert_eq!(commit_type.as_str(), "feat: add thing\n\nRefs: #9");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!("".parse::<ConventionalCommit>(), Err(ParseError::Empty));
+        assert!(matches!(
+            "Add login functionality".parse::<ConventionalCommit>(),
+            Err(ParseError::InvalidHeader(_))
+        ));
+        assert_eq!(
+            "feat(): add".parse::<ConventionalCommit>(),
+            Err(ParseError::EmptyScope)
+        );
+        assert_eq!(
+            "feat: ".parse::<ConventionalCommit>(),
+            Err(ParseError::EmptySubject)
+        );
+    }
+
+    #[test]
+    fn test_commit_type_parse() {
+        let commit_type = CommitType::new("perf(cache): Optimize invalidation".to_string());
+        let commit = commit_type.parse().unwrap();
+        assert_eq!(commit.commit_type, "perf");
+        assert_eq!(commit.header(), "perf(cache): Optimize invalidation");
+    }
+}
@@ -0,0 +1,163 @@
+/// Wrap every line of a commit body to `width` characters.
+///
+/// Words are never split, so URLs stay intact, and `code spans` are kept on one
+/// line. Blank lines, fenced code blocks and lines that start with a list marker
+/// (`-`, `*`, `+`, a digit) or indentation are left alone.
+pub fn wrap_body(body: &str, width: usize) -> String {
+    if width == 0 {
+        return body.to_string();
+    }
+
+    let mut in_fence = false;
+    body.lines()
+        .map(|line| {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+                return line.to_string();
+            }
+            if in_fence || is_preformatted(line) {
+                line.to_string()
+            } else {
+                wrap_line(line, width)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Shorten a subject line to at most `max_length` characters, cutting at a word boundary
+pub fn truncate_subject(subject: &str, max_length: usize) -> String {
+    if subject.chars().count() <= max_length {
+        return subject.to_string();
+    }
+
+    let cut: String = subject.chars().take(max_length).collect();
+    let next_is_space = subject
+        .chars()
+        .nth(max_length)
+        .is_some_and(char::is_whitespace);
+    let shortened = match cut.rfind(char::is_whitespace) {
+        Some(index) if !next_is_space => &cut[..index],
+        _ => cut.as_str(),
+    };
+
+    shortened
+        .trim_end_matches(|c: char| c.is_whitespace() || ",.;:-".contains(c))
+        .to_string()
+}
+
+/// Lists and indented lines are laid out by hand, so wrapping them would break the layout
+fn is_preformatted(line: &str) -> bool {
+    line.starts_with(char::is_whitespace)
+        || line.starts_with(['-', '*', '+'])
+        || line.starts_with(|c: char| c.is_ascii_digit())
+}
+
+fn wrap_line(line: &str, width: usize) -> String {
+    if line.chars().count() <= width {
+        return line.to_string();
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+
+    for word in split_words(line) {
+        if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(&word);
+    }
+    lines.push(current);
+
+    lines.join("\n")
+}
+
+/// Split on whitespace, keeping `code spans` together as a single word
+fn split_words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_code = false;
+
+    for c in text.chars() {
+        if c == '`' {
+            in_code = !in_code;
+        }
+        if c.is_whitespace() && !in_code {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_paragraph() {
+        let body = "This change adds a retry loop around the provider call so transient failures do not abort the commit.";
+        let wrapped = wrap_body(body, 40);
+        assert!(wrapped.lines().all(|line| line.chars().count() <= 40));
+        assert_eq!(
+            wrapped.split_whitespace().collect::<Vec<_>>(),
+            body.split_whitespace().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_wrap_leaves_lists_alone() {
+        let body = "- Added null checks in the response handler and improved the error messages\n* Second item that is also rather long and would need wrapping\n1. Numbered item that is also rather long and would need wrapping\n  continued by hand at an indent that is also longer than the width";
+        assert_eq!(wrap_body(body, 40), body);
+    }
+
+    #[test]
+    fn test_wrap_keeps_urls_and_code_spans() {
+        let url = "https://example.com/a/very/long/path/that/cannot/be/broken/anywhere";
+        let body = format!(
+            "See {} and run `cargo test --workspace --all-targets` before merging.",
+            url
+        );
+        let wrapped = wrap_body(&body, 30);
+        assert!(wrapped.lines().any(|line| line == url));
+        assert!(wrapped
+            .lines()
+            .any(|line| line.contains("`cargo test --workspace --all-targets`")));
+    }
+
+    #[test]
+    fn test_wrap_leaves_code_alone() {
+        let body = "```\nlet value = some_function_with_a_long_name(first_argument, second_argument);\n```\n    indented output line that is longer than the configured wrapping width";
+        assert_eq!(wrap_body(body, 30), body);
+    }
+
+    #[test]
+    fn test_short_lines_unchanged() {
+        let body = "  indented line\n- bullet";
+        assert_eq!(wrap_body(body, 72), body);
+        assert_eq!(wrap_body(body, 0), body);
+    }
+
+    #[test]
+    fn test_truncate_subject() {
+        assert_eq!(truncate_subject("fix: short", 72), "fix: short");
+        assert_eq!(
+            truncate_subject("feat(api): add pagination, filtering and sorting", 30),
+            "feat(api): add pagination"
+        );
+        assert_eq!(
+            truncate_subject("fix: handle nulls now", 17),
+            "fix: handle nulls"
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use log::{info, warn};
+use std::process::Command;
+
+/// Service name the API keys are filed under in the OS keyring
+pub const KEYRING_SERVICE: &str = "iamcommitted";
+
+/// Run `api_key_command` through the shell and return the first line it prints,
+/// the way `pass show` puts the secret on its first line
+pub fn run_api_key_command(command: &str) -> Result<String, Box<dyn std::error::Error>> {
+    info!("Reading the API key from api_key_command");
+    let output = if cfg!(target_os = "windows") {
+        Command::new("cmd").args(["/C", command]).output()
+    } else {
+        Command::new("sh").args(["-c", command]).output()
+    }
+    .map_err(|e| format!("Failed to run api_key_command '{}': {}", command, e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "api_key_command '{}' failed with {}: {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match stdout.lines().next().map(str::trim) {
+        Some(key) if !key.is_empty() => Ok(key.to_string()),
+        _ => Err(format!("api_key_command '{}' printed no key", command).into()),
+    }
+}
+
+/// The key stored for an account (`openai`, `openai:work`) in the OS keyring, `None` when
+/// none is stored or the keyring is unavailable (e.g. no Secret Service on a headless machine)
+pub fn read_keyring(account: &str) -> Option<String> {
+    match keyring_call(account, |entry| entry.get_password()) {
+        Ok(key) => Some(key),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            warn!(
+                "Could not read the {} API key from the keyring: {}",
+                account, e
+            );
+            None
+        }
+    }
+}
+
+/// Store a key in the OS keyring
+pub fn store_api_key(account: &str, key: &str) -> Result<(), Box<dyn std::error::Error>> {
+    keyring_call(account, |entry| entry.set_password(key)).map_err(|e| {
+        format!(
+            "Failed to store the {} API key in the keyring: {}",
+            account, e
+        )
+        .into()
+    })
+}
+
+/// Remove a key from the OS keyring, returning false if none was stored
+pub fn delete_api_key(account: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    match keyring_call(account, |entry| entry.delete_credential()) {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(format!(
+            "Failed to remove the {} API key from the keyring: {}",
+            account, e
+        )
+        .into()),
+    }
+}
+
+/// Run a keyring operation on its own thread. The Secret Service backend drives a
+/// private tokio runtime, which cannot be started from inside the application's.
+fn keyring_call<T, F>(account: &str, operation: F) -> keyring::Result<T>
+where
+    T: Send,
+    F: FnOnce(&keyring::Entry) -> keyring::Result<T> + Send,
+{
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| operation(&keyring::Entry::new(KEYRING_SERVICE, account)?))
+            .join()
+            .unwrap_or_else(|_| {
+                Err(keyring::Error::PlatformFailure(
+                    "the keyring backend panicked".into(),
+                ))
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_api_key_command() {
+        assert_eq!(
+            run_api_key_command("printf 'sk-secret\\nurl: example.com\\n'").unwrap(),
+            "sk-secret"
+        );
+        assert!(run_api_key_command("exit 3").is_err());
+        assert!(run_api_key_command("true").is_err());
+    }
+}
@@ -0,0 +1,694 @@
+use super::{
+    prompts, Config, ConfigSource, Prompts, Resolved, Settings, DEFAULT_CANDIDATES,
+    DEFAULT_HOOK_TIMEOUT_SECS, DEFAULT_PROVIDER, DEFAULT_REPAIR_RETRIES, REPO_KEYS,
+};
+use crate::ai::{
+    FixtureMode, PromptContext, ProviderKind, SecretPolicy, SummariseMode, Template,
+    DEFAULT_FALLBACK_ON, DEFAULT_MAX_ATTEMPTS, DEFAULT_MAX_DIFF_TOKENS,
+    DEFAULT_REQUEST_TIMEOUT_SECS, DEFAULT_RETRY_BACKOFF_MS, DEFAULT_SUMMARY_CONCURRENCY,
+    DEFAULT_SUMMARY_PROMPT,
+};
+use crate::commit_formatter::{FormatOptions, HookFailure, LintRules};
+use std::fs;
+use std::path::Path;
+
+/// Every setting `config show` lists, in display order
+pub const CONFIG_KEYS: &[&str] = &[
+    "profile",
+    "provider",
+    "model",
+    "endpoint",
+    "api_key",
+    "api_key_command",
+    "keyring",
+    "temperature",
+    "max_tokens",
+    "request_timeout",
+    "max_attempts",
+    "retry_backoff_ms",
+    "fallbacks",
+    "fallback_on",
+    "max_diff_tokens",
+    "summarise",
+    "summarise_concurrency",
+    "secrets",
+    "hook_failure",
+    "hook_timeout",
+    "candidates",
+    "repair_retries",
+    "prompts",
+    "format.body_width",
+    "format.max_subject_length",
+    "format.subject_strategy",
+    "format.types",
+    "format.scopes",
+    "format.require_scope",
+    "examples.count",
+    "examples.match_paths",
+    "paths.include",
+    "paths.exclude",
+    "paths.attributes",
+    "ui.color",
+    "ui.banner",
+];
+
+/// Settings that are never printed in full
+const SECRET_KEYS: &[&str] = &["api_key"];
+
+/// A setting as shown by `config show` and `config get`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigEntry {
+    pub key: String,
+    /// Display form of the value, masked for secrets; `None` when unset
+    pub value: Option<String>,
+    pub source: ConfigSource,
+}
+
+/// Result of `config validate`; the configuration is usable when there are no errors
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl Config {
+    /// The value in effect for a key and where it came from
+    pub fn entry(&self, key: &str) -> Result<ConfigEntry, Box<dyn std::error::Error>> {
+        if !CONFIG_KEYS.contains(&key) {
+            return Err(unknown_key(key).into());
+        }
+
+        let resolved = match self.get::<toml::Value>(key) {
+            _ if key == "api_key" => self.api_key()?.map(|resolved| Resolved {
+                value: resolved.value.into(),
+                source: resolved.source,
+            }),
+            // NO_COLOR only gives way to an explicit flag or IAC_COLOR
+            Some(resolved)
+                if key == "ui.color"
+                    && std::env::var_os("NO_COLOR").is_some()
+                    && !matches!(resolved.source, ConfigSource::Cli | ConfigSource::Env(_)) =>
+            {
+                Some(no_color())
+            }
+            None if key == "ui.color" && std::env::var_os("NO_COLOR").is_some() => Some(no_color()),
+            Some(resolved) => Some(resolved),
+            None => self.default_value(key).map(|value| Resolved {
+                value,
+                source: ConfigSource::Default,
+            }),
+        };
+
+        Ok(match resolved {
+            Some(resolved) => {
+                let value = display_value(&resolved.value);
+                ConfigEntry {
+                    key: key.to_string(),
+                    value: Some(if SECRET_KEYS.contains(&key) {
+                        mask_secret(&value)
+                    } else {
+                        value
+                    }),
+                    source: resolved.source,
+                }
+            }
+            None => ConfigEntry {
+                key: key.to_string(),
+                value: None,
+                source: ConfigSource::Default,
+            },
+        })
+    }
+
+    /// Every setting in `CONFIG_KEYS` order
+    pub fn entries(&self) -> Vec<ConfigEntry> {
+        CONFIG_KEYS
+            .iter()
+            .map(|key| {
+                self.entry(key).unwrap_or_else(|e| ConfigEntry {
+                    key: key.to_string(),
+                    value: Some(format!("(error: {})", e)),
+                    source: ConfigSource::Default,
+                })
+            })
+            .collect()
+    }
+
+    /// The built-in default of a key, `None` when the provider picks its own
+    fn default_value(&self, key: &str) -> Option<toml::Value> {
+        let kind = ProviderKind::from_name(&self.provider_name()).ok();
+        let format = FormatOptions::default();
+        let rules = LintRules::default();
+
+        let value = match key {
+            "provider" => DEFAULT_PROVIDER.into(),
+            "model" => kind?.default_model().into(),
+            "endpoint" => kind?.default_endpoint()?.into(),
+            "keyring" => true.into(),
+            "request_timeout" => (DEFAULT_REQUEST_TIMEOUT_SECS as i64).into(),
+            "max_attempts" => (DEFAULT_MAX_ATTEMPTS as i64).into(),
+            "retry_backoff_ms" => (DEFAULT_RETRY_BACKOFF_MS as i64).into(),
+            "fallback_on" => DEFAULT_FALLBACK_ON
+                .iter()
+                .map(|class| class.name())
+                .collect::<Vec<_>>()
+                .into(),
+            "max_diff_tokens" => (DEFAULT_MAX_DIFF_TOKENS as i64).into(),
+            "summarise" => SummariseMode::default().name().into(),
+            "summarise_concurrency" => (DEFAULT_SUMMARY_CONCURRENCY as i64).into(),
+            "secrets" => SecretPolicy::default().name().into(),
+            "hook_failure" => HookFailure::default().name().into(),
+            "hook_timeout" => (DEFAULT_HOOK_TIMEOUT_SECS as i64).into(),
+            "candidates" => DEFAULT_CANDIDATES.into(),
+            "repair_retries" => DEFAULT_REPAIR_RETRIES.into(),
+            "prompts" => self
+                .default_prompts_path()
+                .to_string_lossy()
+                .as_ref()
+                .into(),
+            "format.body_width" => (format.body_width as i64).into(),
+            "format.max_subject_length" => (format.max_subject_length as i64).into(),
+            "format.subject_strategy" => format.subject_strategy.name().into(),
+            "format.types" => rules.types.into(),
+            "format.require_scope" => rules.require_scope.into(),
+            "examples.count" => 0.into(),
+            "examples.match_paths" => false.into(),
+            "paths.attributes" => true.into(),
+            "ui.color" | "ui.banner" => true.into(),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// Write a setting to a configuration file, keeping its comments and layout.
+    ///
+    /// The value is read as a TOML literal (`3`, `true`, `["api", "ui"]`) when that
+    /// fits the setting, otherwise as a string; comma separated lists are accepted
+    /// for list settings.
+    pub fn set_value(
+        path: &Path,
+        key: &str,
+        value: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if SECRET_KEYS.contains(&key) {
+            return Err(format!(
+                "{} is not stored in configuration files, set it in the environment instead",
+                key
+            )
+            .into());
+        }
+        if !CONFIG_KEYS.contains(&key) {
+            return Err(unknown_key(key).into());
+        }
+
+        let contents = if path.exists() {
+            fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read configuration from {:?}: {}", path, e))?
+        } else {
+            String::new()
+        };
+        let document: toml_edit::DocumentMut = contents
+            .parse()
+            .map_err(|e| format!("Invalid configuration in {:?}: {}", path, e))?;
+
+        let list = toml_edit::Value::Array(
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .collect(),
+        );
+        let candidates = [
+            value.trim().parse::<toml_edit::Value>().ok(),
+            Some(toml_edit::Value::from(value)),
+            Some(list),
+        ];
+
+        let mut last_error = String::new();
+        for candidate in candidates.into_iter().flatten() {
+            let mut document = document.clone();
+            set_item(&mut document, key, candidate)
+                .map_err(|e| format!("Cannot set {} in {:?}: {}", key, path, e))?;
+            match toml::from_str::<Settings>(&document.to_string()) {
+                Ok(_) => {
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(path, document.to_string()).map_err(|e| {
+                        format!("Failed to write configuration to {:?}: {}", path, e)
+                    })?;
+                    return Ok(());
+                }
+                Err(e) => last_error = e.message().to_string(),
+            }
+        }
+
+        Err(format!("Invalid value '{}' for {}: {}", value, key, last_error).into())
+    }
+
+    /// Write a setting to a repository's `.iamcommitted.toml` like `set_value`, refusing
+    /// the keys a repository config cannot set
+    pub fn set_repo_value(
+        path: &Path,
+        key: &str,
+        value: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let section = key.split('.').next().unwrap_or(key);
+        if !REPO_KEYS.contains(&section) {
+            return Err(format!(
+                "{} cannot be set in a repository config, set it in the user's config.toml instead. A repository config can set {}",
+                key,
+                REPO_KEYS.join(", ")
+            )
+            .into());
+        }
+        Self::set_value(path, key, value)
+    }
+
+    /// Check everything a run depends on without contacting the provider
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+
+        let profile_error = self.profile().err();
+        if let Some(e) = &profile_error {
+            report.errors.push(e.to_string());
+        }
+
+        let kind = match ProviderKind::from_name(&self.provider_name()) {
+            Ok(kind) => Some(kind),
+            Err(e) => {
+                report.errors.push(e.message);
+                None
+            }
+        };
+
+        if let Err(e) = self.format_options() {
+            report.errors.push(e.to_string());
+        }
+        let summarise = self.summarise_mode().unwrap_or_else(|e| {
+            report.errors.push(e.to_string());
+            SummariseMode::Never
+        });
+        if let Err(e) = self.secret_policy() {
+            report.errors.push(e.to_string());
+        }
+        if let Err(e) = self.hook_failure() {
+            report.errors.push(e.to_string());
+        }
+        if let Err(e) = self.fallbacks() {
+            report.errors.push(e.to_string());
+        }
+        if let Err(e) = self.fallback_on() {
+            report.errors.push(e.to_string());
+        }
+
+        let replaying = match self
+            .fixture_mode()
+            .map(|mode| FixtureMode::from_name(&mode))
+        {
+            Some(Ok(mode)) => mode == FixtureMode::Replay,
+            Some(Err(e)) => {
+                report.errors.push(e.message);
+                false
+            }
+            None => false,
+        };
+
+        if let Some(kind) = kind {
+            // The keyring lookup depends on the profile, which is already reported
+            if kind.requires_api_key() && !replaying && profile_error.is_none() {
+                match self.api_key() {
+                    Ok(Some(_)) => {}
+                    Ok(None) => report.errors.push(format!(
+                        "No API key found, run 'iamcommitted auth login' or set IAC_{prefix}_API_KEY or {prefix}_API_KEY",
+                        prefix = kind.env_prefix()
+                    )),
+                    Err(e) => report.errors.push(e.to_string()),
+                }
+            }
+        }
+
+        let prompts_path = self.prompts_path();
+        if !prompts_path.exists() && prompts_path == self.default_prompts_path() {
+            report.warnings.push(format!(
+                "{:?} does not exist yet, the built-in prompts will be written there on the first run",
+                prompts_path
+            ));
+        } else {
+            match fs::read_to_string(&prompts_path) {
+                Ok(markdown) => match Prompts::parse(&markdown) {
+                    Ok(prompts) => {
+                        let sections = [
+                            ("system", Some(prompts.system_prompt())),
+                            ("user", Some(prompts.user.clone())),
+                            ("assistant prefill", prompts.prefill.clone()),
+                            (
+                                "summary",
+                                Some(
+                                    prompts
+                                        .summary
+                                        .clone()
+                                        .unwrap_or_else(|| DEFAULT_SUMMARY_PROMPT.to_string()),
+                                ),
+                            ),
+                        ];
+                        for (section, source) in &sections {
+                            let Some(source) = source else { continue };
+                            let template = Template::parse(source).and_then(|template| {
+                                template.check_variables(PromptContext::VARIABLES)?;
+                                Ok(template)
+                            });
+                            match template {
+                                Ok(template) => report.warnings.extend(
+                                    template.unknown_variables(PromptContext::VARIABLES).into_iter().map(
+                                        |(name, line)| {
+                                            format!(
+                                                "{:?}: {} prompt: {{{}}} on line {} is not a variable and is sent as written",
+                                                prompts_path, section, name, line
+                                            )
+                                        },
+                                    ),
+                                ),
+                                Err(e) => report.errors.push(format!(
+                                    "{:?}: {} prompt: {}",
+                                    prompts_path, section, e
+                                )),
+                            }
+                        }
+                        if self.example_count() > 0
+                            && !sections.iter().any(|(_, source)| {
+                                source
+                                    .as_deref()
+                                    .is_some_and(|source| source.contains("example_commits"))
+                            })
+                        {
+                            report.warnings.push(format!(
+                                "examples.count is set but {:?} never uses {{example_commits}}",
+                                prompts_path
+                            ));
+                        }
+                        if summarise != SummariseMode::Never
+                            && ![prompts.system_prompt(), prompts.user.clone()]
+                                .iter()
+                                .any(|source| source.contains("change_summaries"))
+                        {
+                            report.warnings.push(format!(
+                                "summarise is {} but {:?} never uses {{change_summaries}}, the model will get the cut down diff",
+                                summarise.name(),
+                                prompts_path
+                            ));
+                        }
+                        // The default copy predating versions is updated on the next run, otherwise only logged
+                        if prompts_path == self.default_prompts_path() && prompts.version.is_none()
+                        {
+                            report.warnings.extend(if prompts::is_legacy_default(&markdown) {
+                                Some(format!(
+                                    "{:?} holds the built-in prompts of an earlier release and will be updated on the next run",
+                                    prompts_path
+                                ))
+                            } else {
+                                prompts::version_warning(None)
+                                    .map(|warning| format!("{:?}: {}", prompts_path, warning))
+                            });
+                        }
+                        report.warnings.extend(
+                            prompts
+                                .warnings()
+                                .into_iter()
+                                .map(|warning| format!("{:?}: {}", prompts_path, warning)),
+                        );
+                    }
+                    Err(e) => report.errors.push(format!("{:?}: {}", prompts_path, e)),
+                },
+                Err(e) => report.errors.push(format!(
+                    "Failed to read prompts from {:?}: {}",
+                    prompts_path, e
+                )),
+            }
+        }
+
+        report
+    }
+}
+
+/// Hide all but the ends of a secret, enough to tell two keys apart
+pub fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() < 12 {
+        return "********".to_string();
+    }
+    let start: String = chars[..3].iter().collect();
+    let end: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}...{}", start, end)
+}
+
+fn unknown_key(key: &str) -> String {
+    format!(
+        "Unknown setting '{}'. Known settings: {}",
+        key,
+        CONFIG_KEYS.join(", ")
+    )
+}
+
+fn no_color() -> Resolved<toml::Value> {
+    Resolved {
+        value: false.into(),
+        source: ConfigSource::Env("NO_COLOR".to_string()),
+    }
+}
+
+/// Strings without quotes and lists comma separated, the way they are passed to `config set`
+fn display_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(value) => value.clone(),
+        toml::Value::Array(items) => items
+            .iter()
+            .map(display_value)
+            .collect::<Vec<_>>()
+            .join(", "),
+        other => other.to_string(),
+    }
+}
+
+/// Set a dotted key, creating its table when needed. The table may be inline, as in
+/// `format = { scopes = ["api"] }`.
+fn set_item(
+    document: &mut toml_edit::DocumentMut,
+    key: &str,
+    value: toml_edit::Value,
+) -> Result<(), String> {
+    match key.split_once('.') {
+        Some((section, name)) => {
+            let table = document
+                .entry(section)
+                .or_insert_with(toml_edit::table)
+                .as_table_like_mut()
+                .ok_or_else(|| format!("{} is not a table", section))?;
+            table.insert(name, toml_edit::value(value));
+        }
+        None => document[key] = toml_edit::value(value),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use tempfile::TempDir;
+
+    fn config_in(dir: &TempDir) -> Config {
+        env::set_var("XDG_CONFIG_HOME", dir.path());
+        let config = Config::for_working_dir(dir.path()).unwrap();
+        env::remove_var("XDG_CONFIG_HOME");
+        config
+    }
+
+    #[test]
+    fn test_entries_show_sources() {
+        let _env = crate::test_support::env_lock();
+        for name in ["IAC_PROVIDER", "IAC_CANDIDATES", "IAC_COLOR", "NO_COLOR"] {
+            env::remove_var(name);
+        }
+        let dir = TempDir::new().unwrap();
+        Config::set_value(
+            &dir.path().join("iamcommitted/config.toml"),
+            "candidates",
+            "5",
+        )
+        .unwrap();
+        env::set_var("IAC_PROVIDER", "ollama");
+        let config = config_in(&dir);
+
+        let provider = config.entry("provider").unwrap();
+        assert_eq!(provider.value.as_deref(), Some("ollama"));
+        assert_eq!(
+            provider.source,
+            ConfigSource::Env("IAC_PROVIDER".to_string())
+        );
+
+        let candidates = config.entry("candidates").unwrap();
+        assert_eq!(candidates.value.as_deref(), Some("5"));
+        assert!(matches!(candidates.source, ConfigSource::User(_)));
+
+        // defaults follow the provider
+        let endpoint = config.entry("endpoint").unwrap();
+        assert_eq!(endpoint.value.as_deref(), Some("http://localhost:11434"));
+        assert_eq!(endpoint.source, ConfigSource::Default);
+        assert_eq!(config.entry("temperature").unwrap().value, None);
+        assert_eq!(
+            config.entry("format.types").unwrap().value.as_deref(),
+            Some(LintRules::default().types.join(", ").as_str())
+        );
+
+        env::set_var("NO_COLOR", "1");
+        assert_eq!(
+            config.entry("ui.color").unwrap().source,
+            ConfigSource::Env("NO_COLOR".to_string())
+        );
+        env::remove_var("NO_COLOR");
+        env::remove_var("IAC_PROVIDER");
+
+        assert!(config.entry("modle").is_err());
+        assert_eq!(config.entries().len(), CONFIG_KEYS.len());
+    }
+
+    #[test]
+    fn test_api_key_is_masked() {
+        let _env = crate::test_support::env_lock();
+        env::remove_var("IAC_PROVIDER");
+        env::remove_var("IAC_OPENAI_API_KEY");
+        env::remove_var("IAC_API_KEY_COMMAND");
+        env::set_var("IAC_KEYRING", "false");
+        env::set_var("OPENAI_API_KEY", "sk-proj-1234567890abcdef");
+        let dir = TempDir::new().unwrap();
+        let config = config_in(&dir);
+
+        let entry = config.entry("api_key").unwrap();
+        assert_eq!(entry.value.as_deref(), Some("sk-...cdef"));
+        assert_eq!(
+            entry.source,
+            ConfigSource::Env("OPENAI_API_KEY".to_string())
+        );
+        env::remove_var("OPENAI_API_KEY");
+        env::remove_var("IAC_KEYRING");
+
+        assert_eq!(mask_secret("short"), "********");
+    }
+
+    #[test]
+    fn test_set_value_keeps_comments() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "# shared settings\nprovider = \"ollama\"\n").unwrap();
+
+        Config::set_value(&path, "model", "qwen2.5-coder").unwrap();
+        Config::set_value(&path, "format.body_width", "80").unwrap();
+        Config::set_value(&path, "format.scopes", "api, ui").unwrap();
+        Config::set_value(&path, "ui.banner", "false").unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with("# shared settings\n"));
+        let settings = Settings::load(&path).unwrap().unwrap();
+        assert_eq!(settings.model.as_deref(), Some("qwen2.5-coder"));
+        assert_eq!(settings.format.body_width, Some(80));
+        assert_eq!(
+            settings.format.scopes,
+            Some(vec!["api".to_string(), "ui".to_string()])
+        );
+        assert_eq!(settings.ui.banner, Some(false));
+
+        assert!(Config::set_value(&path, "candidates", "three").is_err());
+        assert!(Config::set_value(&path, "api_key", "sk-123").is_err());
+        assert!(Config::set_value(&path, "modle", "gpt-4o").is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), contents);
+    }
+
+    #[test]
+    fn test_set_value_in_inline_table() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "format = { scopes = [\"api\"] }\n").unwrap();
+
+        Config::set_value(&path, "format.body_width", "80").unwrap();
+        let settings = Settings::load(&path).unwrap().unwrap();
+        assert_eq!(settings.format.body_width, Some(80));
+        assert_eq!(settings.format.scopes, Some(vec!["api".to_string()]));
+
+        fs::write(&path, "format = \"compact\"\n").unwrap();
+        let error = Config::set_value(&path, "format.body_width", "80")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("format is not a table"), "{}", error);
+    }
+
+    #[test]
+    fn test_set_repo_value_refuses_user_settings() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".iamcommitted.toml");
+
+        Config::set_repo_value(&path, "format.scopes", "api, ui").unwrap();
+        Config::set_repo_value(&path, "candidates", "2").unwrap();
+        Config::set_repo_value(&path, "profile", "cheap").unwrap();
+        for key in [
+            "endpoint",
+            "provider",
+            "api_key_command",
+            "profiles.team.model",
+            "ui.color",
+        ] {
+            let error = Config::set_repo_value(&path, key, "x")
+                .unwrap_err()
+                .to_string();
+            assert!(error.contains("cannot be set in a repository config"));
+        }
+
+        let settings = Settings::load(&path).unwrap().unwrap();
+        assert!(settings.check_repo_safe(&path).is_ok());
+        assert_eq!(settings.candidates, Some(2));
+        assert_eq!(settings.profile.as_deref(), Some("cheap"));
+    }
+
+    #[test]
+    fn test_validate() {
+        let _env = crate::test_support::env_lock();
+        for name in ["IAC_PROVIDER", "IAC_FIXTURE_MODE", "IAC_SUBJECT_STRATEGY"] {
+            env::remove_var(name);
+        }
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join("iamcommitted/config.toml");
+        Config::set_value(&config_path, "provider", "ollama").unwrap();
+
+        let report = config_in(&dir).validate();
+        assert!(report.is_valid(), "{:?}", report.errors);
+        assert_eq!(report.warnings.len(), 1);
+
+        fs::write(
+            dir.path().join("iamcommitted/prompts.md"),
+            "## System Prompt\n\nBe brief.\n",
+        )
+        .unwrap();
+        Config::set_value(&config_path, "format.subject_strategy", "chop").unwrap();
+        let report = config_in(&dir).validate();
+        assert_eq!(report.errors.len(), 2, "{:?}", report.errors);
+        assert!(report.errors[1].contains("prompts.md"));
+
+        fs::write(
+            dir.path().join("iamcommitted/prompts.md"),
+            "## System Prompt\n\nBe brief.\n\n## User Prompt\n\n{#if brnch}{branch}{/if}\n{diff}\n",
+        )
+        .unwrap();
+        let report = config_in(&dir).validate();
+        assert!(report.errors[1].contains("Unknown variable {brnch} on line 1"));
+        assert!(report
+            .warnings
+            .iter()
+            .any(|warning| warning.contains("prompts version 1")));
+    }
+}
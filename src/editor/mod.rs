@@ -0,0 +1,201 @@
+use crate::git::GitClient;
+use log::info;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::process::Command;
+use tempfile::NamedTempFile;
+
+/// Editor used when nothing is configured, matching git's default
+pub const DEFAULT_EDITOR: &str = "vi";
+
+/// Help appended to the message being edited; stripped again afterwards
+const EDIT_INSTRUCTIONS: &str = "\n\
+# Please edit the commit message for your changes. Lines starting\n\
+# with '#' will be ignored, and an empty message aborts the commit.\n";
+
+pub struct Editor {
+    command: String,
+}
+
+#[derive(Debug)]
+pub struct EditorError {
+    pub message: String,
+}
+
+impl std::fmt::Display for EditorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for EditorError {}
+
+impl Editor {
+    pub fn new(command: String) -> Self {
+        Editor { command }
+    }
+
+    /// Resolve the editor the way git does: GIT_EDITOR, core.editor, VISUAL, EDITOR, then vi
+    pub fn from_git(git_client: &GitClient) -> Self {
+        let core_editor = git_client.get_config("core.editor").ok().flatten();
+        Self::new(resolve_editor_command(core_editor))
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Open `message` in the editor and return the edited text with comment lines removed
+    pub fn edit(&self, message: &str) -> Result<String, EditorError> {
+        let mut temp_file = NamedTempFile::new().map_err(|e| EditorError {
+            message: format!("Failed to create temporary file: {}", e),
+        })?;
+        write!(temp_file, "{}\n{}", message, EDIT_INSTRUCTIONS)
+            .and_then(|_| temp_file.flush())
+            .map_err(|e| EditorError {
+                message: format!("Failed to write temporary file: {}", e),
+            })?;
+
+        info!("Opening editor: {}", self.command);
+        let status = self
+            .shell_command(&temp_file.path().to_string_lossy())
+            .status()
+            .map_err(|e| EditorError {
+                message: format!("Failed to start editor '{}': {}", self.command, e),
+            })?;
+
+        if !status.success() {
+            return Err(EditorError {
+                message: format!("Editor '{}' exited with {}", self.command, status),
+            });
+        }
+
+        let edited = fs::read_to_string(temp_file.path()).map_err(|e| EditorError {
+            message: format!("Failed to read edited message: {}", e),
+        })?;
+
+        Ok(strip_comments(&edited))
+    }
+
+    /// Run the editor through the shell, like git, so commands with arguments work
+    fn shell_command(&self, path: &str) -> Command {
+        if cfg!(target_os = "windows") {
+            let mut command = Command::new("cmd");
+            command
+                .arg("/C")
+                .arg(format!("{} \"{}\"", self.command, path));
+            command
+        } else {
+            let mut command = Command::new("sh");
+            command
+                .arg("-c")
+                .arg(format!("{} \"$@\"", self.command))
+                .arg(&self.command)
+                .arg(path);
+            command
+        }
+    }
+}
+
+/// Pick the editor command from the environment and the `core.editor` setting
+pub fn resolve_editor_command(core_editor: Option<String>) -> String {
+    let non_empty = |value: String| {
+        if value.trim().is_empty() {
+            None
+        } else {
+            Some(value)
+        }
+    };
+
+    env::var("GIT_EDITOR")
+        .ok()
+        .and_then(non_empty)
+        .or_else(|| core_editor.and_then(non_empty))
+        .or_else(|| env::var("VISUAL").ok().and_then(non_empty))
+        .or_else(|| env::var("EDITOR").ok().and_then(non_empty))
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string())
+}
+
+/// Line git places above the diff in `git commit --verbose`; everything below it is dropped
+const SCISSORS_LINE: &str = "# ------------------------ >8 ------------------------";
+
+/// Clean up an edited message like `git commit --cleanup=strip`: cut at the scissors
+/// line, drop `#` lines and trailing whitespace, collapse blank runs and trim blank
+/// lines at either end
+pub fn strip_comments(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+
+    for line in text.lines() {
+        if line == SCISSORS_LINE {
+            break;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        let line = line.trim_end();
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_editor_precedence() {
+        let _env = crate::test_support::env_lock();
+        env::remove_var("GIT_EDITOR");
+        env::remove_var("VISUAL");
+        env::remove_var("EDITOR");
+        assert_eq!(resolve_editor_command(None), DEFAULT_EDITOR);
+
+        env::set_var("EDITOR", "nano");
+        assert_eq!(resolve_editor_command(None), "nano");
+
+        env::set_var("VISUAL", "code --wait");
+        assert_eq!(resolve_editor_command(None), "code --wait");
+
+        assert_eq!(resolve_editor_command(Some("emacs".to_string())), "emacs");
+
+        env::set_var("GIT_EDITOR", "vim");
+        assert_eq!(resolve_editor_command(Some("emacs".to_string())), "vim");
+
+        env::remove_var("GIT_EDITOR");
+        env::remove_var("VISUAL");
+        env::remove_var("EDITOR");
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        let edited = "# leading comment\n\nfeat: add login   \n\n\n# Please edit\nBody line\n#\n\n";
+        assert_eq!(strip_comments(edited), "feat: add login\n\nBody line");
+
+        let verbose =
+            "fix: typo\n# ------------------------ >8 ------------------------\ndiff --git a/x b/x";
+        assert_eq!(strip_comments(verbose), "fix: typo");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_edit_with_editor_arguments() {
+        let editor = Editor::new("sed -i -e 's/add/remove/'".to_string());
+        let edited = editor.edit("feat: add login").unwrap();
+        assert_eq!(edited, "feat: remove login");
+    }
+
+    #[test]
+    fn test_edit_with_missing_editor() {
+        let editor = Editor::new("definitely-not-an-editor-iac".to_string());
+        assert!(editor.edit("feat: add login").is_err());
+    }
+}
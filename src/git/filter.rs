@@ -0,0 +1,178 @@
+use regex::Regex;
+
+/// Git attributes that keep a file's changes out of the AI diff, with the value
+/// that does it
+const EXCLUDING_ATTRIBUTES: &[(&str, &str)] = &[
+    ("linguist-generated", "set"),
+    ("linguist-generated", "true"),
+    ("linguist-vendored", "set"),
+    ("linguist-vendored", "true"),
+    ("diff", "unset"),
+];
+
+/// Which staged files have their changes sent to the AI. Files left out are still
+/// listed by name, so the model knows they changed.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    include: Vec<(String, Regex)>,
+    exclude: Vec<(String, Regex)>,
+    /// Leave out files marked `linguist-generated`, `linguist-vendored` or `-diff`
+    /// in `.gitattributes`
+    pub attributes: bool,
+}
+
+impl PathFilter {
+    /// A filter sending only files matching one of the `include` globs, if there are
+    /// any, and none matching an `exclude` glob. Globs follow `.gitignore`: without a
+    /// slash they match a file or directory name anywhere, `**` spans directories.
+    pub fn new(include: &[String], exclude: &[String], attributes: bool) -> Self {
+        let compile = |globs: &[String]| {
+            globs
+                .iter()
+                .filter(|glob| !glob.trim().is_empty())
+                .map(|glob| (glob.clone(), glob_regex(glob.trim())))
+                .collect()
+        };
+        PathFilter {
+            include: compile(include),
+            exclude: compile(exclude),
+            attributes,
+        }
+    }
+
+    /// Whether the filter can leave anything out
+    pub fn is_active(&self) -> bool {
+        !self.include.is_empty() || !self.exclude.is_empty() || self.attributes
+    }
+
+    /// Why the glob rules leave a path out, `None` if its changes are sent
+    pub fn exclusion(&self, path: &str) -> Option<String> {
+        if !self.include.is_empty() && !self.include.iter().any(|(_, regex)| regex.is_match(path)) {
+            return Some("not matched by paths.include".to_string());
+        }
+        self.exclude
+            .iter()
+            .find(|(_, regex)| regex.is_match(path))
+            .map(|(glob, _)| format!("excluded by '{}'", glob))
+    }
+}
+
+/// Why the `git check-attr -z` value of an attribute leaves a file out, if it does
+pub(crate) fn attribute_exclusion(attribute: &str, value: &str) -> Option<String> {
+    EXCLUDING_ATTRIBUTES
+        .iter()
+        .any(|&(name, excluding)| name == attribute && excluding == value)
+        .then(|| match attribute {
+            "diff" => "marked -diff".to_string(),
+            _ => format!("marked {}", attribute),
+        })
+}
+
+/// Translate a `.gitignore` style glob into a regex over repository-relative paths
+fn glob_regex(glob: &str) -> Regex {
+    let directory = glob.ends_with('/');
+    let glob = glob.trim_end_matches('/');
+    // A slash anywhere but at the end anchors the glob at the repository root
+    let anchored = glob.contains('/');
+    let glob = glob.trim_start_matches('/');
+
+    let mut pattern = String::from(if anchored { "^" } else { "^(?:.*/)?" });
+    let chars: Vec<char> = glob.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    pattern.push_str("(?:.*/)?");
+                    i += 3;
+                } else {
+                    pattern.push_str(".*");
+                    i += 2;
+                }
+                continue;
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            '[' => match chars[i..].iter().position(|&c| c == ']') {
+                Some(end) if end > 1 => {
+                    let class: String = chars[i + 1..i + end].iter().collect();
+                    let class = class
+                        .strip_prefix('!')
+                        .map_or(class.clone(), |rest| format!("^{}", rest));
+                    pattern.push('[');
+                    pattern.push_str(&class.replace('\\', "\\\\").replace('[', "\\["));
+                    pattern.push(']');
+                    i += end + 1;
+                    continue;
+                }
+                _ => pattern.push_str("\\["),
+            },
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    // A directory matches everything below it, a file name only itself
+    pattern.push_str(if directory { "/.*$" } else { "(?:/.*)?$" });
+    Regex::new(&pattern).unwrap_or_else(|_| Regex::new(&regex::escape(glob)).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(glob: &str, path: &str) -> bool {
+        glob_regex(glob).is_match(path)
+    }
+
+    #[test]
+    fn test_globs_follow_gitignore() {
+        assert!(matches("*.lock", "Cargo.lock"));
+        assert!(matches("*.lock", "web/yarn.lock"));
+        assert!(!matches("*.lock", "src/lock.rs"));
+        assert!(matches("vendor/", "vendor/lib/a.js"));
+        assert!(matches("vendor", "third_party/vendor/a.js"));
+        assert!(matches("/docs/*.md", "docs/guide.md"));
+        assert!(!matches("/docs/*.md", "docs/api/guide.md"));
+        assert!(!matches("docs/*.md", "web/docs/guide.md"));
+        assert!(matches("src/**/*.snap", "src/ui/__snapshots__/app.snap"));
+        assert!(matches("src/**/*.snap", "src/app.snap"));
+        assert!(matches("**/*.min.js", "public/app.min.js"));
+        assert!(matches("file?.[ch]", "lib/file1.c"));
+        assert!(!matches("file?.[!ch]", "lib/file1.c"));
+    }
+
+    #[test]
+    fn test_exclusion() {
+        let filter = PathFilter::new(
+            &["src/**".to_string(), "Cargo.toml".to_string()],
+            &["*.snap".to_string()],
+            false,
+        );
+        assert!(filter.is_active());
+        assert_eq!(filter.exclusion("src/main.rs"), None);
+        assert_eq!(filter.exclusion("Cargo.toml"), None);
+        assert_eq!(
+            filter.exclusion("readme.md").as_deref(),
+            Some("not matched by paths.include")
+        );
+        assert_eq!(
+            filter.exclusion("src/ui/app.snap").as_deref(),
+            Some("excluded by '*.snap'")
+        );
+        assert!(!PathFilter::default().is_active());
+    }
+
+    #[test]
+    fn test_attribute_exclusion() {
+        assert_eq!(
+            attribute_exclusion("linguist-generated", "true").as_deref(),
+            Some("marked linguist-generated")
+        );
+        assert_eq!(
+            attribute_exclusion("diff", "unset").as_deref(),
+            Some("marked -diff")
+        );
+        assert_eq!(attribute_exclusion("linguist-generated", "false"), None);
+        assert_eq!(attribute_exclusion("diff", "unspecified"), None);
+    }
+}
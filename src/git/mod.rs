@@ -1,9 +1,16 @@
+mod filter;
+
+pub use filter::PathFilter;
+
 use colored::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::process::Command;
 use std::process::Output;
 
 pub struct GitClient {
     working_dir: Option<String>,
+    path_filter: PathFilter,
 }
 
 #[derive(Debug)]
@@ -19,32 +26,214 @@ impl std::fmt::Display for GitError {
 
 impl std::error::Error for GitError {}
 
+impl Default for GitClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl GitClient {
     pub fn new() -> Self {
-        GitClient { working_dir: None }
+        GitClient {
+            working_dir: None,
+            path_filter: PathFilter::default(),
+        }
     }
 
     pub fn with_working_dir(dir: String) -> Self {
         GitClient {
             working_dir: Some(dir),
+            path_filter: PathFilter::default(),
         }
     }
 
+    /// Use `filter` to decide which files' changes `get_changes_for_ai` includes
+    pub fn with_path_filter(mut self, filter: PathFilter) -> Self {
+        self.path_filter = filter;
+        self
+    }
+
     pub fn get_staged_changes(&self) -> Result<String, GitError> {
         let output = self.run_git_command(&["diff", "--cached", "--diff-algorithm=minimal"])?;
         Ok(String::from_utf8_lossy(&output.stdout).to_string())
     }
 
+    /// The staged diff as sent to the AI. Files the path filter leaves out keep a
+    /// `diff --git` header with a note saying why and how many lines changed, instead
+    /// of their changes.
+    pub fn get_changes_for_ai(&self) -> Result<String, GitError> {
+        if !self.path_filter.is_active() {
+            return self.get_staged_changes();
+        }
+
+        let output =
+            self.run_git_command(&["diff", "--cached", "--numstat", "-z", "--no-renames"])?;
+        let numstat = String::from_utf8_lossy(&output.stdout).to_string();
+        let files: Vec<(&str, String)> = numstat
+            .split('\0')
+            .filter_map(|entry| {
+                let mut fields = entry.splitn(3, '\t');
+                let (added, removed, path) = (fields.next()?, fields.next()?, fields.next()?);
+                let stat = if added == "-" {
+                    "binary".to_string()
+                } else {
+                    format!("+{} -{}", added, removed)
+                };
+                Some((path, stat))
+            })
+            .collect();
+
+        let attributes = if self.path_filter.attributes {
+            let paths: Vec<&str> = files.iter().map(|(path, _)| *path).collect();
+            self.get_excluding_attributes(&paths)?
+        } else {
+            HashMap::new()
+        };
+        let excluded: Vec<(&str, String, &String)> = files
+            .iter()
+            .filter_map(|(path, stat)| {
+                self.path_filter
+                    .exclusion(path)
+                    .or_else(|| attributes.get(*path).cloned())
+                    .map(|reason| (*path, reason, stat))
+            })
+            .collect();
+        if excluded.is_empty() {
+            return self.get_staged_changes();
+        }
+
+        let pathspecs: Vec<String> = excluded
+            .iter()
+            .map(|(path, _, _)| format!(":(top,exclude,literal){}", path))
+            .collect();
+        let mut args = vec!["diff", "--cached", "--diff-algorithm=minimal", "--", ":/"];
+        args.extend(pathspecs.iter().map(String::as_str));
+        let output = self.run_git_command(&args)?;
+        let mut diff = String::from_utf8_lossy(&output.stdout).to_string();
+        for (path, reason, stat) in excluded {
+            diff.push_str(&format!(
+                "diff --git a/{path} b/{path}\nChanges left out, {reason}: {stat}\n",
+                path = path,
+                reason = reason,
+                stat = stat
+            ));
+        }
+        Ok(diff)
+    }
+
+    /// Why `.gitattributes` leaves each of `paths` out of the AI diff, for those it does
+    fn get_excluding_attributes(
+        &self,
+        paths: &[&str],
+    ) -> Result<HashMap<String, String>, GitError> {
+        let Some(toplevel) = self.get_toplevel()? else {
+            return Ok(HashMap::new());
+        };
+        let toplevel = toplevel.to_string_lossy().to_string();
+        let mut args = vec![
+            "-C",
+            &toplevel,
+            "check-attr",
+            "-z",
+            "linguist-generated",
+            "linguist-vendored",
+            "diff",
+            "--",
+        ];
+        args.extend_from_slice(paths);
+        let output = self.run_git_command(&args)?;
+        if !output.status.success() {
+            return Ok(HashMap::new());
+        }
+
+        // NUL separated path, attribute, value triples
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let fields: Vec<&str> = stdout.split('\0').collect();
+        let mut reasons = HashMap::new();
+        for triple in fields.chunks_exact(3) {
+            if let Some(reason) = filter::attribute_exclusion(triple[1], triple[2]) {
+                reasons.entry(triple[0].to_string()).or_insert(reason);
+            }
+        }
+        Ok(reasons)
+    }
+
     pub fn get_staged_files(&self) -> Result<String, GitError> {
         let output = self.run_git_command(&["diff", "--cached", "--name-only"])?;
         Ok(String::from_utf8_lossy(&output.stdout).to_string())
     }
 
+    /// Summary of the staged changes, as printed by `git diff --cached --stat`
+    pub fn get_diffstat(&self) -> Result<String, GitError> {
+        let output = self.run_git_command(&["diff", "--cached", "--stat"])?;
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string())
+    }
+
+    /// Subjects of the last `count` commits, newest first; empty before the first commit
+    pub fn get_recent_commits(&self, count: usize) -> Result<Vec<String>, GitError> {
+        let output =
+            self.run_git_command(&["log", &format!("-{}", count), "--format=%s", "--no-merges"])?;
+        if !output.status.success() {
+            return Ok(Vec::new());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Full messages of the last `count` commits, newest first, limited to commits touching
+    /// `paths` unless it is empty; empty before the first commit
+    pub fn get_commit_messages(
+        &self,
+        count: usize,
+        paths: &[String],
+    ) -> Result<Vec<String>, GitError> {
+        let count = format!("-{}", count);
+        let mut args = vec!["log", &count, "--format=%B%x00", "--no-merges"];
+        if !paths.is_empty() {
+            args.push("--");
+            args.extend(paths.iter().map(String::as_str));
+        }
+        let output = self.run_git_command(&args)?;
+        if !output.status.success() {
+            return Ok(Vec::new());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .split('\0')
+            .map(str::trim)
+            .filter(|message| !message.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
     pub fn has_staged_changes(&self) -> Result<bool, GitError> {
         let changes = self.get_staged_changes()?;
         Ok(!changes.is_empty())
     }
 
+    /// Read a git config value, `None` when it is not set
+    pub fn get_config(&self, key: &str) -> Result<Option<String>, GitError> {
+        let output = self.run_git_command(&["config", "--get", key])?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok(Some(value))
+    }
+
+    /// The root of the working tree, `None` outside a repository
+    pub fn get_toplevel(&self) -> Result<Option<PathBuf>, GitError> {
+        let output = self.run_git_command(&["rev-parse", "--show-toplevel"])?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let toplevel = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok(Some(PathBuf::from(toplevel)))
+    }
+
     pub fn commit(&self, message: &str) -> Result<Output, GitError> {
         self.run_git_command(&["commit", "-m", message])
     }
@@ -101,6 +290,7 @@ impl GitClient {
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_borrows_for_generic_args)]
 mod tests {
     use super::*;
     use std::fs::File;
@@ -214,4 +404,115 @@ mod tests {
         let log = String::from_utf8_lossy(&log_output.stdout);
         assert!(log.contains("test commit"));
     }
+
+    #[test]
+    fn test_get_diffstat_and_recent_commits() {
+        let temp_dir = setup_test_repo();
+        let git_client = GitClient::with_working_dir(temp_dir.path().to_string_lossy().to_string());
+        assert!(git_client.get_recent_commits(5).unwrap().is_empty());
+
+        for (file_name, message) in [("a.txt", "feat: add a"), ("b.txt", "fix: repair b")] {
+            std::fs::write(temp_dir.path().join(file_name), "content\n").unwrap();
+            Command::new("git")
+                .args(&["add", file_name])
+                .current_dir(temp_dir.path())
+                .output()
+                .unwrap();
+            git_client.commit(message).unwrap();
+        }
+        assert_eq!(
+            git_client.get_recent_commits(5).unwrap(),
+            ["fix: repair b", "feat: add a"]
+        );
+        assert_eq!(git_client.get_recent_commits(1).unwrap(), ["fix: repair b"]);
+        assert_eq!(
+            git_client
+                .get_commit_messages(5, &["a.txt".to_string()])
+                .unwrap(),
+            ["feat: add a"]
+        );
+
+        std::fs::write(temp_dir.path().join("c.txt"), "one\ntwo\n").unwrap();
+        Command::new("git")
+            .args(["add", "c.txt"])
+            .current_dir(temp_dir.path())
+            .output()
+            .unwrap();
+        let diffstat = git_client.get_diffstat().unwrap();
+        assert!(diffstat.contains("c.txt"));
+        assert!(diffstat.contains("1 file changed, 2 insertions(+)"));
+    }
+
+    #[test]
+    fn test_get_changes_for_ai() {
+        let temp_dir = setup_test_repo();
+        let files = [
+            ("src/lib.rs", "pub fn answer() -> u32 {\n    42\n}\n"),
+            ("Cargo.lock", "version = 3\nchecksum = \"abc\"\n"),
+            ("web/bundle.js", "var a=1;\n"),
+            (".gitattributes", "web/bundle.js linguist-generated\n"),
+        ];
+        for (path, content) in files {
+            let path = temp_dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        Command::new("git")
+            .args(["add", "."])
+            .current_dir(temp_dir.path())
+            .output()
+            .unwrap();
+
+        let dir = temp_dir.path().to_string_lossy().to_string();
+        let unfiltered = GitClient::with_working_dir(dir.clone());
+        assert_eq!(
+            unfiltered.get_changes_for_ai().unwrap(),
+            unfiltered.get_staged_changes().unwrap()
+        );
+
+        let git_client = GitClient::with_working_dir(dir).with_path_filter(PathFilter::new(
+            &[],
+            &["*.lock".to_string()],
+            true,
+        ));
+        let diff = git_client.get_changes_for_ai().unwrap();
+        assert!(diff.contains("+pub fn answer() -> u32 {"));
+        assert!(diff.contains("+web/bundle.js linguist-generated"));
+        assert!(!diff.contains("checksum"));
+        assert!(!diff.contains("var a=1;"));
+        assert!(diff.ends_with(
+            "diff --git a/Cargo.lock b/Cargo.lock\nChanges left out, excluded by '*.lock': +2 -0\n\
+             diff --git a/web/bundle.js b/web/bundle.js\nChanges left out, marked linguist-generated: +1 -0\n"
+        ));
+    }
+
+    #[test]
+    fn test_get_config() {
+        let temp_dir = setup_test_repo();
+        let git_client = GitClient::with_working_dir(temp_dir.path().to_string_lossy().to_string());
+
+        assert_eq!(
+            git_client.get_config("user.name").unwrap(),
+            Some("Test User".to_string())
+        );
+        assert_eq!(git_client.get_config("iamcommitted.unset").unwrap(), None);
+    }
+
+    #[test]
+    fn test_get_toplevel() {
+        let temp_dir = setup_test_repo();
+        let nested = temp_dir.path().join("nested");
+        std::fs::create_dir(&nested).unwrap();
+        let git_client = GitClient::with_working_dir(nested.to_string_lossy().to_string());
+
+        let toplevel = git_client.get_toplevel().unwrap().unwrap();
+        assert_eq!(
+            toplevel.canonicalize().unwrap(),
+            temp_dir.path().canonicalize().unwrap()
+        );
+
+        let outside = TempDir::new().unwrap();
+        let git_client = GitClient::with_working_dir(outside.path().to_string_lossy().to_string());
+        assert_eq!(git_client.get_toplevel().unwrap(), None);
+    }
 }
@@ -1,4 +1,38 @@
 pub mod ai;
 pub mod commit_formatter;
 pub mod config;
+pub mod editor;
 pub mod git;
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use crate::config::Config;
+    use std::env;
+    use std::sync::{Mutex, MutexGuard};
+    use tempfile::TempDir;
+
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Serialise tests that mutate process-wide environment variables
+    pub fn env_lock() -> MutexGuard<'static, ()> {
+        ENV_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// A configuration that keeps its files in a temporary directory instead of the
+    /// user's config directory, for tests not holding `env_lock`
+    pub fn temp_config() -> (TempDir, Config) {
+        let _env = env_lock();
+        load_temp_config()
+    }
+
+    /// `temp_config` for tests that already hold `env_lock`
+    pub fn load_temp_config() -> (TempDir, Config) {
+        let dir = TempDir::new().unwrap();
+        env::set_var("XDG_CONFIG_HOME", dir.path());
+        let config = Config::new();
+        env::remove_var("XDG_CONFIG_HOME");
+        (dir, config.unwrap())
+    }
+}
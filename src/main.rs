@@ -1,9 +1,10 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use colored::Colorize;
 use log::{info, warn};
 use std::fs;
-use std::io::Write;
-use std::{env, io, process::Command};
+use std::io;
+use std::io::{IsTerminal, Write};
+use termion::input::TermRead;
 
 fn setup_logging(verbose: bool) -> Result<(), Box<dyn std::error::Error>> {
     let log_dir = Config::get_log_dir()?;
@@ -31,15 +32,16 @@ fn setup_logging(verbose: bool) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-mod ai;
-mod commit_formatter;
-mod config;
-mod git;
+use iamcommitted::ai::{AIClient, ChatMessage, FixtureMode, PromptContext, ProviderKind};
+use iamcommitted::commit_formatter::{
+    heuristic_message, rank_candidates, CommitFormatter, FormatOptions, HookFailure, LintRules,
+    Linter, Severity,
+};
+use iamcommitted::config::{delete_api_key, store_api_key, Config, Settings, KEYRING_SERVICE};
+use iamcommitted::editor::{strip_comments, Editor};
+use iamcommitted::git::GitClient;
 
-use crate::ai::AIClient;
-use crate::commit_formatter::CommitFormatter;
-use crate::config::Config;
-use crate::git::GitClient;
+const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[derive(Parser)]
 #[command(
@@ -49,6 +51,27 @@ use crate::git::GitClient;
     long_about = "IAmCommitted uses OpenAI's API to analyze your staged changes and generate meaningful commit messages following conventional commit standards.\n\n\
                   ENVIRONMENT VARIABLES:\n\
                   The application requires OpenAI API configuration through environment variables.\n\n\
+                  Provider selection:\n  \
+                  IAC_PROVIDER          - AI provider to use: openai, ollama, mock (default: openai)\n  \
+                  IAC_CANDIDATES        - Number of suggestions to generate (default: 3, 1 in the hook)\n  \
+                  IAC_TEMPERATURE       - Sampling temperature (provider default)\n  \
+                  IAC_MAX_TOKENS        - Maximum tokens to generate (provider default)\n  \
+                  IAC_REQUEST_TIMEOUT   - Seconds to wait for the model, 0 for no limit (default: 60)\n  \
+                  IAC_MAX_ATTEMPTS      - Attempts per request on rate limits and outages (default: 3)\n  \
+                  IAC_RETRY_BACKOFF_MS  - Wait before the first retry, doubling after (default: 500)\n  \
+                  IAC_MAX_DIFF_TOKENS   - Estimated diff tokens sent to the model, 0 for no limit (default: 12000)\n  \
+                  IAC_SUMMARISE         - Summarise large diffs part by part: never, auto, always (default: never)\n  \
+                  IAC_SUMMARISE_CONCURRENCY - Summary requests sent at once (default: 4)\n  \
+                  IAC_SECRETS           - Secrets in the diff: redact, block, off (default: redact)\n  \
+                  IAC_HOOK_FAILURE      - When the hook can't generate a message: block, warn, heuristic (default: heuristic)\n  \
+                  IAC_HOOK_TIMEOUT      - Seconds the hook may spend on a message, 0 for no limit (default: 30)\n  \
+                  IAC_REPAIR_RETRIES    - Re-prompts when a message breaks the commit rules (default: 2)\n  \
+                  IAC_BODY_WIDTH        - Column the message body is wrapped at, 0 to disable (default: 72)\n  \
+                  IAC_SUBJECT_STRATEGY  - Overlong subjects: warn or truncate (default: warn)\n  \
+                  IAC_EXAMPLES          - Commits from the repository's history used as examples (default: 0)\n  \
+                  IAC_EXAMPLES_MATCH_PATHS - Prefer example commits touching the staged directories\n\n\
+                  API keys are read from IAC_API_KEY_COMMAND (a command printing the key), then the\n\
+                  OS keyring ('iamcommitted auth login', IAC_KEYRING=false to skip), then the variables below.\n\n\
                   IAmCommitted-specific (takes precedence):\n  \
                   IAC_OPENAI_API_KEY    - Your OpenAI API key for IAmCommitted\n  \
                   IAC_OPENAI_MODEL      - Model to use (default: gpt-4o-mini)\n  \
@@ -57,14 +80,31 @@ use crate::git::GitClient;
                   OPENAI_API_KEY        - Your OpenAI API key\n  \
                   OPENAI_MODEL          - Model to use (default: gpt-4o-mini)\n  \
                   OPENAI_ENDPOINT       - Custom OpenAI endpoint (optional)\n\n\
+                  Local Ollama (IAC_PROVIDER=ollama, no API key needed):\n  \
+                  IAC_OLLAMA_ENDPOINT   - Ollama server address (default: http://localhost:11434)\n  \
+                  IAC_OLLAMA_MODEL      - Model to use (default: llama3.2)\n\n\
+                  Offline testing:\n  \
+                  IAC_MOCK_RESPONSE     - Fixed response returned by the mock provider\n  \
+                  IAC_FIXTURE_MODE      - record or replay request/response fixtures\n  \
+                  IAC_FIXTURE_DIR       - Fixture directory (default: fixtures/ in the config directory)\n\n\
                   CONFIGURATION DIRECTORY:\n\
                   IAmCommitted stores configuration files in platform-specific directories:\n\n\
                   Linux/Unix: ~/.config/iamcommitted/ (or $XDG_CONFIG_HOME/iamcommitted/)\n  \
                   macOS:      ~/Library/Application Support/iamcommitted/\n  \
                   Windows:    %USERPROFILE%\\AppData\\Roaming\\iamcommitted\\\n\n\
                   Configuration files:\n  \
+                  config.toml           - Provider, model, generation, [format] and [ui] settings (optional)\n                           \
+                  api_key_command = \"pass show openai\" reads the API key from a command\n  \
                   prompts.md            - AI prompts used for commit message generation\n                           \
                   (created automatically with defaults on first run)\n\n\
+                  A repository can check in its commit conventions ([format], [examples], [paths],\n\
+                  prompts, candidates, repair_retries) and a default profile as .iamcommitted.toml;\n\
+                  every one between the current directory and the repository root applies, the\n\
+                  nearest first.\n\n\
+                  Named profiles ([profiles.<name>] in config.toml) bundle provider, endpoint, model,\n\
+                  key source and prompts; pick one with --profile, IAC_PROFILE or profile = \"<name>\".\n\n\
+                  Settings are resolved in this order: command line flags, environment variables,\n\
+                  the selected profile, .iamcommitted.toml, config.toml, built-in defaults.\n\n\
                   Logs are stored separately in:\n  \
                   Linux/Unix: ~/.local/state/iamcommitted/logs/ (or $XDG_STATE_HOME/iamcommitted/logs/)\n  \
                   macOS:      ~/Library/Logs/iamcommitted/\n  \
@@ -72,20 +112,66 @@ use crate::git::GitClient;
                   EXAMPLES:\n  \
                   # Set IAmCommitted-specific API key:\n  \
                   export IAC_OPENAI_API_KEY='your-key-here'\n\n  \
+                  # Or keep it in the OS keyring instead of the environment:\n  \
+                  iamcommitted auth login\n\n  \
                   # Run with verbose logging:\n  \
                   iamcommitted -v\n\n  \
+                  # Use the stronger model set up as a profile for a big refactor:\n  \
+                  iamcommitted --profile strong\n\n  \
+                  # List the models served by a local Ollama:\n  \
+                  IAC_PROVIDER=ollama iamcommitted models\n\n  \
                   # Use as git hook:\n  \
-                  iamcommitted prepare-commit-msg .git/COMMIT_EDITMSG"
+                  iamcommitted prepare-commit-msg .git/COMMIT_EDITMSG\n\n  \
+                  # Check a message against the lint rules:\n  \
+                  echo 'feat(api): add login' | iamcommitted lint --scopes api,ui\n\n  \
+                  # See which settings are in effect and where they come from:\n  \
+                  iamcommitted config show\n\n  \
+                  # Share the allowed scopes with everyone working on the repository:\n  \
+                  iamcommitted config set --repo format.scopes api,ui"
 )]
 struct Cli {
     /// Enable verbose mode to print logs to console as well
     #[arg(long = "verbose", short = 'v')]
     verbose: bool,
 
+    /// Named profile from the configuration to use
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// AI provider to use (openai, ollama, mock)
+    #[arg(long, global = true)]
+    provider: Option<String>,
+
+    /// Model to use
+    #[arg(long, global = true)]
+    model: Option<String>,
+
+    /// API endpoint of the provider
+    #[arg(long, global = true)]
+    endpoint: Option<String>,
+
+    /// Number of suggestions to generate
+    #[arg(long, global = true)]
+    candidates: Option<u32>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
+impl Cli {
+    /// Settings given as flags, which override the environment and config.toml
+    fn overrides(&self) -> Settings {
+        Settings {
+            profile: self.profile.clone(),
+            provider: self.provider.clone(),
+            model: self.model.clone(),
+            endpoint: self.endpoint.clone(),
+            candidates: self.candidates,
+            ..Settings::default()
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Prepares the commit message (for git prepare-commit-msg hook)
@@ -102,14 +188,417 @@ enum Commands {
         #[arg(index = 3, required = false)]
         commit_sha1: Option<String>,
     },
+    /// Lists the models available from the configured provider
+    Models,
+    /// Checks a commit message against the lint rules
+    Lint {
+        /// File containing the message (reads stdin when omitted or '-')
+        #[arg(index = 1)]
+        file: Option<String>,
+
+        #[command(flatten)]
+        rules: LintArgs,
+    },
+    /// Validates the commit message (for git commit-msg hook)
+    CommitMsg {
+        /// Path to the commit message file
+        #[arg(index = 1)]
+        commit_msg_file_path: String,
+
+        #[command(flatten)]
+        rules: LintArgs,
+    },
+    /// Stores or removes the provider's API key in the OS keyring
+    Auth {
+        #[command(subcommand)]
+        action: AuthAction,
+    },
+    /// Shows, changes and checks the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuthAction {
+    /// Prompts for the API key and stores it in the OS keyring (reads stdin when piped)
+    Login,
+    /// Removes the stored API key from the OS keyring
+    Logout,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Prints every setting in effect and where its value comes from
+    Show,
+    /// Prints the value of one setting, e.g. `format.body_width`
+    Get { key: String },
+    /// Writes a setting to config.toml (or .iamcommitted.toml with --repo)
+    Set {
+        key: String,
+        value: String,
+
+        /// Write to the repository's .iamcommitted.toml instead of the user config
+        #[arg(long)]
+        repo: bool,
+    },
+    /// Prints the locations of the configuration, prompts and log files
+    Path,
+    /// Checks the settings and prompts.md without contacting the provider
+    Validate,
+}
+
+/// Lint rule overrides shared by `lint` and `commit-msg`
+#[derive(Args)]
+struct LintArgs {
+    /// Allowed commit types, comma separated
+    #[arg(long, value_delimiter = ',')]
+    types: Option<Vec<String>>,
+
+    /// Allowed scopes, comma separated (any scope when omitted)
+    #[arg(long, value_delimiter = ',')]
+    scopes: Option<Vec<String>>,
+
+    /// Require every commit to have a scope
+    #[arg(long)]
+    require_scope: bool,
+
+    /// Maximum length of the subject line
+    #[arg(long)]
+    max_subject_length: Option<usize>,
+
+    /// Maximum length of body lines, 0 to disable
+    #[arg(long)]
+    body_width: Option<usize>,
+}
+
+impl LintArgs {
+    /// The configured rules with the flags applied on top
+    fn rules(&self, mut rules: LintRules) -> LintRules {
+        if let Some(types) = &self.types {
+            rules.types = types.clone();
+        }
+        if let Some(scopes) = &self.scopes {
+            rules.scopes = scopes.clone();
+        }
+        rules.require_scope |= self.require_scope;
+        if let Some(max_subject_length) = self.max_subject_length {
+            rules.max_subject_length = max_subject_length;
+        }
+        if let Some(body_width) = self.body_width {
+            rules.body_wrap_width = body_width;
+        }
+        rules
+    }
+}
+
+/// Lint a message and print each violation, returning false if any rule errored
+fn print_lint_report(message: &str, rules: LintRules) -> bool {
+    let report = Linter::new(rules).lint(message);
+
+    for violation in &report.violations {
+        match violation.severity {
+            Severity::Error => eprintln!("{} {}", "✖".red(), violation),
+            Severity::Warning => eprintln!("{} {}", "⚠".yellow(), violation),
+        }
+    }
+    info!(
+        "Lint finished with {} violation(s)",
+        report.violations.len()
+    );
+
+    !report.has_errors()
+}
+
+/// Entries of the interactive selection menu
+enum MenuOption {
+    UseSuggested,
+    ChooseAlternative,
+    Regenerate,
+    Edit,
+    Cancel,
+}
+
+impl MenuOption {
+    fn label(&self) -> &'static str {
+        match self {
+            MenuOption::UseSuggested => "Use the suggested message ✅ (default)",
+            MenuOption::ChooseAlternative => "Choose an alternative",
+            MenuOption::Regenerate => "Regenerate with feedback 🔄",
+            MenuOption::Edit => "Edit the message manually",
+            MenuOption::Cancel => "Cancel",
+        }
+    }
 }
 
-async fn generate_formatted_commit_message(
+/// Show a prompt and read a number from stdin, `None` if the input is not a number
+fn prompt_number(prompt: &str) -> Option<usize> {
+    prompt_line(prompt).parse::<usize>().ok()
+}
+
+/// Show a prompt and read a line of free text from stdin
+fn prompt_line(prompt: &str) -> String {
+    print!("{}", prompt);
+    io::stdout().flush().unwrap();
+
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .expect("Failed to read line");
+
+    input.trim().to_string()
+}
+
+/// Render a number as a keycap emoji (1️⃣, 2️⃣, ...) for single digits
+fn keycap(number: usize) -> String {
+    if number < 10 {
+        format!("{}\u{fe0f}\u{20e3}", number)
+    } else {
+        format!("{}.", number)
+    }
+}
+
+/// Look up the API key for the configured provider: `api_key_command`, the OS keyring, then
+/// IAC_<PROVIDER>_API_KEY over <PROVIDER>_API_KEY (e.g. IAC_OPENAI_API_KEY over OPENAI_API_KEY).
+/// Providers that need no key (and fixture replay) get an empty key.
+fn resolve_api_key(config: &Config, hint: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let kind = ProviderKind::from_name(&config.provider_name())?;
+    let replaying = config
+        .fixture_mode()
+        .map(|mode| FixtureMode::from_name(&mode))
+        .transpose()?
+        == Some(FixtureMode::Replay);
+    if !kind.requires_api_key() || replaying {
+        return Ok(String::new());
+    }
+
+    config.api_key()?.map(|key| key.value).ok_or_else(|| {
+        format!(
+            "Error: No API key stored with 'iamcommitted auth login' and neither IAC_{prefix}_API_KEY nor {prefix}_API_KEY environment variable is set{hint}",
+            prefix = kind.env_prefix(),
+            hint = hint
+        )
+        .into()
+    })
+}
+
+/// Run an `auth` subcommand against the keyring entry of the configured provider and profile
+fn run_auth_command(config: &Config, action: AuthAction) -> Result<(), Box<dyn std::error::Error>> {
+    let account = config.keyring_account()?;
+
+    match action {
+        AuthAction::Login => {
+            let key = if io::stdin().is_terminal() {
+                print!("API key for {}: ", account);
+                io::stdout().flush()?;
+                let key = io::stdin().read_passwd(&mut io::stdout())?;
+                println!();
+                key.unwrap_or_default()
+            } else {
+                io::read_to_string(io::stdin())?
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            let key = key.trim();
+            if key.is_empty() {
+                return Err("No API key entered".into());
+            }
+
+            store_api_key(&account, key)?;
+            println!(
+                "{} Stored the {} API key in the keyring (service '{}')",
+                "✔".green(),
+                account,
+                KEYRING_SERVICE
+            );
+        }
+        AuthAction::Logout => {
+            if delete_api_key(&account)? {
+                println!(
+                    "{} Removed the {} API key from the keyring",
+                    "✔".green(),
+                    account
+                );
+            } else {
+                println!("No {} API key is stored in the keyring", account);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run a `config` subcommand, returning false when it failed
+fn run_config_command(
+    config: &Config,
+    action: ConfigAction,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    match action {
+        ConfigAction::Show => {
+            let entries = config.entries();
+            let width = entries
+                .iter()
+                .map(|entry| entry.key.len())
+                .max()
+                .unwrap_or(0);
+            for entry in entries {
+                println!(
+                    "{:width$}  {}  {}",
+                    entry.key,
+                    entry.value.as_deref().unwrap_or("(not set)"),
+                    format!("({})", entry.source).dimmed(),
+                    width = width
+                );
+            }
+            println!(
+                "{:width$}  {}",
+                "logs",
+                Config::get_log_dir()?.display(),
+                width = width
+            );
+        }
+        ConfigAction::Get { key } => match config.entry(&key)?.value {
+            Some(value) => println!("{}", value),
+            None => return Ok(false),
+        },
+        ConfigAction::Set { key, value, repo } => {
+            let path = if repo {
+                config
+                    .repo_config_path()
+                    .ok_or("Not inside a git repository, cannot use --repo")?
+            } else {
+                config.config_path()
+            };
+            if repo {
+                Config::set_repo_value(&path, &key, &value)?;
+            } else {
+                Config::set_value(&path, &key, &value)?;
+            }
+            println!("{} Set {} in {}", "✔".green(), key, path.display());
+        }
+        ConfigAction::Path => {
+            println!("user config:  {}", config.config_path().display());
+            match config.repo_config_path() {
+                Some(path) if path.exists() => println!("repo config:  {}", path.display()),
+                Some(path) => println!("repo config:  {} (not present)", path.display()),
+                None => println!("repo config:  (not in a repository)"),
+            }
+            println!("prompts:      {}", config.prompts_path().display());
+            println!("logs:         {}", Config::get_log_dir()?.display());
+        }
+        ConfigAction::Validate => {
+            let report = config.validate();
+            for error in &report.errors {
+                eprintln!("{} {}", "✖".red(), error);
+            }
+            for warning in &report.warnings {
+                eprintln!("{} {}", "⚠".yellow(), warning);
+            }
+            if !report.is_valid() {
+                return Ok(false);
+            }
+            println!("{} Configuration is valid", "✔".green());
+        }
+    }
+    Ok(true)
+}
+
+/// How many messages to generate and the rules they have to pass
+struct GenerationSettings {
+    candidate_count: u32,
+    /// Re-prompts allowed when no candidate passes the lint rules after repair
+    repair_retries: u32,
+    lint_rules: LintRules,
+    format_options: FormatOptions,
+    /// Commits from the history shown to the model as examples, 0 for none
+    example_count: u32,
+    examples_match_paths: bool,
+    /// Print progress while a large diff is summarised
+    show_progress: bool,
+}
+
+impl GenerationSettings {
+    fn from_config(config: &Config) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(GenerationSettings {
+            candidate_count: config.candidate_count(),
+            repair_retries: config.repair_retries(),
+            lint_rules: config.lint_rules(),
+            format_options: config.format_options()?,
+            example_count: config.example_count(),
+            examples_match_paths: config.examples_match_paths(),
+            show_progress: false,
+        })
+    }
+}
+
+/// Format and repair raw model output
+fn format_candidates(raw_messages: Vec<String>, settings: &GenerationSettings) -> Vec<String> {
+    raw_messages
+        .into_iter()
+        .map(|raw_message| {
+            info!("Raw AI-generated message: {}", raw_message);
+            let formatter =
+                CommitFormatter::new(raw_message).with_options(settings.format_options.clone());
+            format!("{}", formatter.format_repaired(&settings.lint_rules))
+        })
+        .collect()
+}
+
+/// The version, profile and model line at the top of interactive mode, naming the
+/// fallback model once one has taken over
+fn model_header(profile: Option<&str>, ai_client: &AIClient) -> String {
+    let model = if ai_client.active_model() == ai_client.get_model() {
+        ai_client.get_model().to_string()
+    } else {
+        format!(
+            "{} (fallback for {})",
+            ai_client.active_model(),
+            ai_client.get_model()
+        )
+    };
+    match profile {
+        Some(profile) => format!("v{} | Profile: {} | Model: {}", VERSION, profile, model),
+        None => format!("v{} | Model: {}", VERSION, model),
+    }
+}
+
+/// Generate candidate messages, formatted, repaired, de-duplicated and ranked best first.
+///
+/// When no candidate passes the lint rules, the model is asked to fix the top one,
+/// up to `settings.repair_retries` times. Messages passing the rules are listed first.
+async fn generate_formatted_commit_messages(
     git_client: &GitClient,
     ai_client: &AIClient,
-) -> Result<String, Box<dyn std::error::Error>> {
-    // Get the full diff for AI processing
-    let diff = git_client.get_staged_changes()?;
+    settings: &GenerationSettings,
+    history: &[ChatMessage],
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    // The full diff for AI processing with the branch, file list, recent commits and the
+    // like for the prompt templates
+    let mut context = PromptContext::gather(git_client, &git_client.get_changes_for_ai()?)?;
+    if settings.example_count > 0 {
+        context.add_example_commits(
+            git_client,
+            settings.example_count as usize,
+            settings.examples_match_paths,
+            &settings.lint_rules,
+        )?;
+        info!(
+            "Using {} commit(s) from the history as examples",
+            context.example_commits.len()
+        );
+    }
+
+    // Secrets are replaced once, before anything is logged or sent
+    let findings = ai_client.redact_context(&mut context)?;
+    if settings.show_progress && !findings.is_empty() {
+        println!(
+            "🔒 Replaced {} possible secret(s) in the prompt with placeholders",
+            findings.len()
+        );
+    }
+    let diff = std::mem::take(&mut context.diff);
     info!(
         "Retrieved diff for AI processing (first 500 chars):\n{}",
         diff.chars().take(500).collect::<String>()
@@ -124,32 +613,244 @@ async fn generate_formatted_commit_message(
         }
     }
 
-    // Generate commit message using AI
-    let raw_message = ai_client.generate_commit_message(&diff).await?;
-    info!("Raw AI-generated message: {}", raw_message);
+    // Keep big commits within the model's token budget
+    context.diff = ai_client.fit_diff(&diff);
+
+    // Very large changes are summarised part by part from the full diff
+    let print_progress = |done: usize, total: usize| {
+        print!("\r🧩 Summarising changes {}/{}", done, total);
+        if done == total {
+            println!();
+        }
+        let _ = io::stdout().flush();
+    };
+    let progress: Option<&(dyn Fn(usize, usize) + Sync)> = if settings.show_progress {
+        Some(&print_progress)
+    } else {
+        None
+    };
+    ai_client
+        .add_change_summaries(&mut context, &diff, progress)
+        .await?;
+
+    // Generate commit messages using AI
+    let raw_messages = ai_client
+        .generate_commit_messages_with_context(&context, history, settings.candidate_count)
+        .await?;
+
+    // Format each candidate, then merge duplicates
+    let mut ranked = rank_candidates(format_candidates(raw_messages, settings));
+    if ranked.is_empty() {
+        return Err("The AI returned only empty commit messages".into());
+    }
+
+    let linter = Linter::new(settings.lint_rules.clone());
+    let passes = |message: &String| !linter.lint(message).has_errors();
+    let mut repair_history = history.to_vec();
+    let mut attempt = 0;
+
+    while !ranked.iter().any(passes) && attempt < settings.repair_retries {
+        attempt += 1;
+        let errors: Vec<String> = linter
+            .lint(&ranked[0])
+            .violations
+            .iter()
+            .filter(|violation| violation.severity == Severity::Error)
+            .map(|violation| format!("- {}", violation.message))
+            .collect();
+        warn!(
+            "Generated message breaks the commit rules, re-prompting (attempt {}/{}):\n{}",
+            attempt,
+            settings.repair_retries,
+            errors.join("\n")
+        );
+
+        let feedback = format!(
+            "The message does not follow the Conventional Commits rules:\n{}\nFix these problems and keep the meaning.",
+            errors.join("\n")
+        );
+        repair_history.extend(AIClient::revision_request(&ranked[0], &feedback));
+        let raw_messages = ai_client
+            .generate_commit_messages_with_context(&context, &repair_history, 1)
+            .await?;
+
+        let mut retried = format_candidates(raw_messages, settings);
+        retried.append(&mut ranked);
+        ranked = rank_candidates(retried);
+    }
+
+    if !ranked.iter().any(passes) {
+        warn!(
+            "No generated message passes the commit rules after {} re-prompt(s), using the best effort",
+            attempt
+        );
+    }
+    let (mut valid, invalid): (Vec<String>, Vec<String>) = ranked.into_iter().partition(passes);
+    valid.extend(invalid);
+    for message in &valid {
+        info!("Formatted commit message: {}", message);
+    }
+
+    Ok(valid)
+}
+
+/// Generate a message and write it to the hook's commit message file
+async fn write_hook_commit_message(
+    git_client: &GitClient,
+    ai_client: &AIClient,
+    settings: &GenerationSettings,
+    commit_msg_file_path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Check for staged changes. Even if none, AI might generate a message for an empty commit if allowed.
+    if !git_client.has_staged_changes()? {
+        warn!("No staged changes detected by git_client.has_staged_changes() in hook mode. Proceeding to generate message based on (likely empty) diff.");
+    }
+
+    let candidates =
+        generate_formatted_commit_messages(git_client, ai_client, settings, &[]).await?;
+    // Candidates are ranked, so the hook takes the top one
+    fs::write(commit_msg_file_path, &candidates[0])?;
+    info!(
+        "Successfully wrote AI-generated commit message to {}",
+        commit_msg_file_path
+    );
+    Ok(())
+}
+
+/// Deal with a hook message that could not be generated as `hook_failure` says: fail the
+/// hook, which aborts the commit, or warn and leave a message for the user to finish. A
+/// message already in the file, e.g. when amending, is kept as it is.
+fn recover_hook_failure(
+    policy: HookFailure,
+    git_client: &GitClient,
+    lint_rules: &LintRules,
+    commit_msg_file_path: &str,
+    error: Box<dyn std::error::Error>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if policy == HookFailure::Block {
+        eprintln!("Error generating commit message for hook: {}", error);
+        return Err(error);
+    }
+    warn!(
+        "Generating the commit message failed, continuing as hook_failure is '{}': {}",
+        policy.name(),
+        error
+    );
+    eprintln!(
+        "{} Could not generate a commit message: {}",
+        "⚠".yellow(),
+        error
+    );
+
+    let existing = fs::read_to_string(commit_msg_file_path).unwrap_or_default();
+    let mut note = String::from("# iamcommitted could not generate a message:\n");
+    for line in error.to_string().lines() {
+        note.push_str(&format!("#   {}\n", line));
+    }
+
+    let suggestion = if policy == HookFailure::Heuristic && strip_comments(&existing).is_empty() {
+        match git_client.get_staged_files() {
+            Ok(files) => {
+                let files: Vec<&str> = files.lines().collect();
+                Some(heuristic_message(&files, lint_rules))
+            }
+            Err(e) => {
+                warn!(
+                    "Could not list the staged files for a fallback message: {}",
+                    e
+                );
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let contents = match suggestion {
+        Some(message) => {
+            info!("Using a message built from the staged files: {}", message);
+            format!(
+                "{}\n\n{}# This one was put together from the staged file names; edit it as needed.\n{}",
+                message, note, existing
+            )
+        }
+        None => format!("{}{}", note, existing),
+    };
+    fs::write(commit_msg_file_path, contents)?;
+    Ok(())
+}
 
-    // Format the commit message
-    let formatter = CommitFormatter::new(raw_message.clone());
-    let formatted_commit = formatter.format();
-    let final_message = format!("{}", formatted_commit);
-    info!("Formatted commit message: {}", final_message);
+/// Whether the `prepare-commit-msg` hook leaves the message alone for this commit source:
+/// a message given with -m or -F, or a commit template
+fn keeps_own_message(commit_source: Option<&str>) -> bool {
+    matches!(commit_source, Some("message" | "template"))
+}
 
-    Ok(final_message)
+/// Handle a configuration the `prepare-commit-msg` hook could not load like any other
+/// failure of the hook, with the default `hook_failure` policy and lint rules, so a
+/// broken config file does not stop commits
+fn recover_hook_config_error(
+    git_client: &GitClient,
+    commit_msg_file_path: &str,
+    error: Box<dyn std::error::Error>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    recover_hook_failure(
+        HookFailure::default(),
+        git_client,
+        &LintRules::default(),
+        commit_msg_file_path,
+        format!("Could not load the configuration: {}", error).into(),
+    )
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    const VERSION: &str = env!("CARGO_PKG_VERSION");
-
     let cli = Cli::parse();
 
+    // The prepare-commit-msg hook must not stop a commit over its own setup
+    let hook_mode = matches!(cli.command, Some(Commands::PrepareCommitMsg { .. }));
+
     // Set up logging with verbose flag if provided
-    setup_logging(cli.verbose)?;
+    if let Err(e) = setup_logging(cli.verbose) {
+        if !hook_mode {
+            return Err(e);
+        }
+        eprintln!("{} Could not set up logging: {}", "⚠".yellow(), e);
+    }
 
     if cli.verbose {
         println!("Verbose mode enabled. Logs will be printed to console.");
     }
 
+    // The hooks and lint run unattended, so a mistyped key should not stop them
+    let lenient = matches!(
+        cli.command,
+        Some(
+            Commands::PrepareCommitMsg { .. } | Commands::Lint { .. } | Commands::CommitMsg { .. }
+        )
+    );
+    let loaded = if lenient {
+        Config::new_lenient()
+    } else {
+        Config::new()
+    };
+    let config = match loaded {
+        Ok(config) => config.with_overrides(cli.overrides()),
+        Err(e) => match &cli.command {
+            Some(Commands::PrepareCommitMsg {
+                commit_msg_file_path,
+                commit_source,
+                ..
+            }) if !keeps_own_message(commit_source.as_deref()) => {
+                return recover_hook_config_error(&GitClient::new(), commit_msg_file_path, e);
+            }
+            Some(Commands::PrepareCommitMsg { .. }) => return Ok(()),
+            _ => return Err(e),
+        },
+    };
+    if !config.color() {
+        colored::control::set_override(false);
+    }
+
     match cli.command {
         Some(Commands::PrepareCommitMsg {
             commit_msg_file_path,
@@ -162,7 +863,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 info!("Commit source: {}", source);
                 // If the user is providing a message via -m or -F, or using a template,
                 // we should not overwrite it with an AI-generated one.
-                if source == "message" || source == "template" {
+                if keeps_own_message(Some(source)) {
                     info!(
                         "Commit source is '{}', skipping AI message generation.",
                         source
@@ -174,62 +875,129 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 info!("Commit SHA1: {}", sha1);
             }
 
-            // Check for API key - IAC_OPENAI_API_KEY takes precedence over OPENAI_API_KEY
-            let api_key = env::var("IAC_OPENAI_API_KEY")
-                .or_else(|_| env::var("OPENAI_API_KEY"))
-                .map_err(|_| {
-                    "Error: Neither IAC_OPENAI_API_KEY nor OPENAI_API_KEY environment variable is set for prepare-commit-msg hook."
-                })?;
-            let git_client = GitClient::new();
-            let config = Config::new()?;
-            let ai_client = AIClient::new(api_key, config)?;
+            // An unknown policy is a failure like any other, handled by the default one
+            let (hook_failure, invalid_policy) = match config.hook_failure() {
+                Ok(policy) => (policy, None),
+                Err(e) => (HookFailure::default(), Some(e)),
+            };
+            let lint_rules = config.lint_rules();
+            let hook_timeout = config.hook_timeout();
+            let git_client = GitClient::new().with_path_filter(config.path_filter());
+            let generated = async {
+                if let Some(e) = invalid_policy {
+                    return Err(e);
+                }
+                let api_key = resolve_api_key(&config, " for prepare-commit-msg hook.")?;
+                let settings = GenerationSettings {
+                    candidate_count: config.hook_candidate_count(),
+                    ..GenerationSettings::from_config(&config)?
+                };
+                let ai_client = AIClient::new(api_key, config)?;
+                write_hook_commit_message(&git_client, &ai_client, &settings, &commit_msg_file_path)
+                    .await
+            };
 
-            // Check for staged changes. Even if none, AI might generate a message for an empty commit if allowed.
-            if !git_client.has_staged_changes()? {
-                warn!("No staged changes detected by git_client.has_staged_changes() in hook mode. Proceeding to generate message based on (likely empty) diff.");
+            // Retries and fallbacks must not hold up the commit for minutes
+            let generated = match hook_timeout {
+                Some(limit) => tokio::time::timeout(limit, generated)
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(format!(
+                            "No message within {}s (hook_timeout / IAC_HOOK_TIMEOUT)",
+                            limit.as_secs()
+                        )
+                        .into())
+                    }),
+                None => generated.await,
+            };
+
+            // A missing key or an unreachable model need not stop the commit
+            match generated {
+                Ok(()) => Ok(()),
+                Err(e) => recover_hook_failure(
+                    hook_failure,
+                    &git_client,
+                    &lint_rules,
+                    &commit_msg_file_path,
+                    e,
+                ),
+            }
+        }
+        Some(Commands::Lint { file, rules }) => {
+            let message = match file.as_deref() {
+                None | Some("-") => io::read_to_string(io::stdin())?,
+                Some(path) => fs::read_to_string(path)?,
+            };
+
+            if !print_lint_report(&strip_comments(&message), rules.rules(config.lint_rules())) {
+                std::process::exit(1);
             }
+            println!("{} Commit message passes all lint rules", "✔".green());
+            Ok(())
+        }
+        Some(Commands::CommitMsg {
+            commit_msg_file_path,
+            rules,
+        }) => {
+            info!("Running in commit-msg hook mode.");
+            let message = fs::read_to_string(&commit_msg_file_path)?;
 
-            match generate_formatted_commit_message(&git_client, &ai_client).await {
-                Ok(commit_message_content) => {
-                    fs::write(&commit_msg_file_path, &commit_message_content)?;
-                    info!(
-                        "Successfully wrote AI-generated commit message to {}",
-                        commit_msg_file_path
-                    );
-                }
-                Err(e) => {
-                    eprintln!("Error generating commit message for hook: {}", e);
-                    // Propagate the error to potentially halt the commit process
-                    return Err(e);
-                }
+            if !print_lint_report(&strip_comments(&message), rules.rules(config.lint_rules())) {
+                eprintln!(
+                    "\nCommit aborted: the message does not follow the commit conventions.\n\
+                     Your message is saved in {}. Use 'git commit --no-verify' to bypass this check.",
+                    commit_msg_file_path
+                );
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Some(Commands::Auth { action }) => run_auth_command(&config, action),
+        Some(Commands::Config { action }) => {
+            if !run_config_command(&config, action)? {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Some(Commands::Models) => {
+            let api_key = resolve_api_key(&config, ".")?;
+            let ai_client = AIClient::new(api_key, config)?;
+
+            for model in ai_client.list_models().await? {
+                println!("{}", model);
             }
             Ok(())
         }
         None => {
             // Interactive mode (original behavior)
-            println!(
-                "{}",
-                r#"
+            if config.banner() {
+                println!(
+                    "{}",
+                    r#"
     ____               _____                 _ __  __         __
     /  _/ ___ ___ _    / ___/__  __ _  __ _  (_) /_/ /____ ___/ /
    _/ /  / _ `/  ' \  / /__/ _ \/  ' \/  ' \/ / __/ __/ -_) _  /
   /___/  \_,_/_/_/_/  \___/\___/_/_/_/_/_/_/_/\__/\__/\__/\_,_/
 
       "#
-                .green()
-            );
-            // Check for API key - IAC_OPENAI_API_KEY takes precedence over OPENAI_API_KEY
-            let api_key = env::var("IAC_OPENAI_API_KEY")
-                .or_else(|_| env::var("OPENAI_API_KEY"))
-                .map_err(|_| {
-                    "Error: Neither IAC_OPENAI_API_KEY nor OPENAI_API_KEY environment variable is set. Please set one of these environment variables with your OpenAI API key to use this application."
-                })?;
-
-            let git_client = GitClient::new();
-            let config = Config::new()?;
+                    .green()
+                );
+            }
+            let api_key = resolve_api_key(
+                &config,
+                ". Please set one of these environment variables with your API key to use this application.",
+            )?;
+
+            let git_client = GitClient::new().with_path_filter(config.path_filter());
+            let settings = GenerationSettings {
+                show_progress: true,
+                ..GenerationSettings::from_config(&config)?
+            };
+            let profile = config.profile()?;
             let ai_client = AIClient::new(api_key, config)?;
 
-            println!("v{} | Model: {}", VERSION, ai_client.get_model());
+            let mut header = model_header(profile.as_deref(), &ai_client);
+            println!("{}", header);
             println!("\n{}", "🔍 Analysing Changes...".blue());
             println!("-----------------------------------------");
 
@@ -250,73 +1018,140 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
             println!("-----------------------------------------");
 
-            let commit_message = generate_formatted_commit_message(&git_client, &ai_client).await?;
+            // Earlier answers and feedback, replayed to the model on each regeneration
+            let mut history: Vec<ChatMessage> = Vec::new();
 
-            println!("\n📝 Suggested Commit Message:");
-            println!("---------------------------------------------------");
-            println!("{}", commit_message);
-            println!("---------------------------------------------------");
+            let final_message = loop {
+                let candidates = generate_formatted_commit_messages(
+                    &git_client,
+                    &ai_client,
+                    &settings,
+                    &history,
+                )
+                .await?;
+                let commit_message = candidates[0].clone();
+                let alternatives = &candidates[1..];
 
-            println!("\nPlease select an option:");
-            println!("[1] Use the suggested message ✅ (default)");
-            println!("[2] Edit the message manually");
-            println!("[3] Cancel");
+                // Say so when a fallback model wrote the message
+                let current_header = model_header(profile.as_deref(), &ai_client);
+                if current_header != header {
+                    println!("\n{}", current_header);
+                    header = current_header;
+                }
 
-            print!("\nEnter your choice (1-3): ⌨️  ");
-            io::stdout().flush().unwrap();
+                println!("\n📝 Suggested Commit Message:");
+                println!("---------------------------------------------------");
+                println!("{}", commit_message);
+                println!("---------------------------------------------------");
 
-            let mut input = String::new();
-            io::stdin()
-                .read_line(&mut input)
-                .expect("Failed to read line");
+                if !alternatives.is_empty() {
+                    println!("\n💡 Alternative Suggestions:");
+                    for (index, alternative) in alternatives.iter().enumerate() {
+                        println!(
+                            "{} {}",
+                            keycap(index + 1),
+                            alternative.lines().next().unwrap_or_default()
+                        );
+                    }
+                }
 
-            let num_result = input.trim().parse::<u32>();
+                // Only offer to choose an alternative when there is one
+                let mut options = vec![MenuOption::UseSuggested];
+                if !alternatives.is_empty() {
+                    options.push(MenuOption::ChooseAlternative);
+                }
+                options.push(MenuOption::Regenerate);
+                options.push(MenuOption::Edit);
+                options.push(MenuOption::Cancel);
 
-            if num_result.is_err() {
-                println!("\n{} Please enter a valid number (1-3)\n", "❌".red());
-                return Ok(());
-            }
+                println!("\nPlease select an option:");
+                for (index, option) in options.iter().enumerate() {
+                    println!("[{}] {}", index + 1, option.label());
+                }
 
-            let num = num_result.unwrap();
-            let final_message = match num {
-                1 => commit_message,
-                2 => {
-                    // Edit commit message using nano
-                    // Note: std::fs is already imported at the top level
-                    use tempfile::NamedTempFile; // Keep this local as it's specific to this block
-
-                    let mut temp_file = NamedTempFile::new()?;
-                    write!(temp_file, "{}", commit_message)?;
-                    temp_file.flush()?;
-
-                    let status = Command::new("nano")
-                        .arg(temp_file.path())
-                        .status()
-                        .expect("Failed to open nano");
-
-                    if !status.success() {
-                        println!("\nFailed to edit commit message using nano");
+                let num = match prompt_number(&format!(
+                    "\nEnter your choice (1-{}): ⌨️  ",
+                    options.len()
+                )) {
+                    Some(num) => num,
+                    None => {
+                        println!(
+                            "\n{} Please enter a valid number (1-{})\n",
+                            "❌".red(),
+                            options.len()
+                        );
                         return Ok(());
                     }
+                };
 
-                    let edited_message = fs::read_to_string(temp_file.path())?;
+                match options.get(num.wrapping_sub(1)) {
+                    Some(MenuOption::UseSuggested) => break commit_message,
+                    Some(MenuOption::ChooseAlternative) => {
+                        let alternative = prompt_number(&format!(
+                            "\nEnter the alternative to use (1-{}): ⌨️  ",
+                            alternatives.len()
+                        ))
+                        .and_then(|n| alternatives.get(n.wrapping_sub(1)));
 
-                    let formatter = CommitFormatter::new(edited_message);
-                    let formatted_commit = formatter.format();
-                    format!("{}", formatted_commit)
-                }
-                _ => {
-                    println!("\nCommit cancelled\n");
-                    return Ok(());
+                        match alternative {
+                            Some(alternative) => break alternative.clone(),
+                            None => {
+                                println!(
+                                    "\n{} Please enter a valid number (1-{})\n",
+                                    "❌".red(),
+                                    alternatives.len()
+                                );
+                                return Ok(());
+                            }
+                        }
+                    }
+                    Some(MenuOption::Regenerate) => {
+                        let mut feedback = prompt_line(
+                            "\n💬 What should change? (e.g. \"shorter\", \"this is a fix not a feat\"): ",
+                        );
+                        if feedback.is_empty() {
+                            feedback = "Try a different wording.".to_string();
+                        }
+                        info!("Regenerating with feedback: {}", feedback);
+
+                        history.extend(AIClient::revision_request(&commit_message, &feedback));
+                        println!("\n{}", "🔄 Regenerating...".blue());
+                    }
+                    Some(MenuOption::Edit) => {
+                        let editor = Editor::from_git(&git_client);
+                        let edited_message = match editor.edit(&commit_message) {
+                            Ok(edited_message) => edited_message,
+                            Err(e) => {
+                                warn!("Failed to edit commit message: {}", e);
+                                println!("\n{} {}", "❌".red(), e);
+                                return Ok(());
+                            }
+                        };
+
+                        if edited_message.trim().is_empty() {
+                            println!("\nAborting commit due to empty commit message.\n");
+                            return Ok(());
+                        }
+
+                        // The user laid the message out by hand, so it is not re-wrapped
+                        let formatter =
+                            CommitFormatter::new(edited_message).with_options(FormatOptions {
+                                body_width: 0,
+                                ..settings.format_options.clone()
+                            });
+                        let formatted_commit = formatter.format();
+                        break format!("{}", formatted_commit);
+                    }
+                    Some(MenuOption::Cancel) | None => {
+                        println!("\nCommit cancelled\n");
+                        return Ok(());
+                    }
                 }
             };
 
-            if num <= 2 {
-                // Only commit if option 1 or 2 was chosen
-                git_client
-                    .commit_with_details(&final_message)
-                    .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)?;
-            }
+            git_client
+                .commit_with_details(&final_message)
+                .map_err(|e| Box::new(e) as Box<dyn std::error::Error>)?;
             Ok(())
         }
     }
@@ -326,6 +1161,329 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 mod tests {
     use super::*;
     use clap::CommandFactory;
+    use iamcommitted::ai::MockProvider;
+    use std::process::Command;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    fn setup_staged_repo() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        for args in [
+            vec!["init"],
+            vec!["config", "user.name", "Test User"],
+            vec!["config", "user.email", "test@example.com"],
+        ] {
+            Command::new("git")
+                .args(&args)
+                .current_dir(temp_dir.path())
+                .output()
+                .unwrap();
+        }
+
+        fs::write(temp_dir.path().join("hello.txt"), "hello world\n").unwrap();
+        Command::new("git")
+            .args(["add", "hello.txt"])
+            .current_dir(temp_dir.path())
+            .output()
+            .unwrap();
+
+        temp_dir
+    }
+
+    fn settings(candidate_count: u32) -> GenerationSettings {
+        GenerationSettings {
+            candidate_count,
+            repair_retries: 2,
+            lint_rules: LintRules::default(),
+            format_options: FormatOptions::default(),
+            example_count: 0,
+            examples_match_paths: false,
+            show_progress: false,
+        }
+    }
+
+    /// Serialise tests that point XDG_CONFIG_HOME elsewhere
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    /// A configuration keeping its files in a temporary directory instead of the user's
+    fn temp_config() -> (TempDir, Config) {
+        let _env = ENV_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let dir = TempDir::new().unwrap();
+        std::env::set_var("XDG_CONFIG_HOME", dir.path());
+        let config = Config::new();
+        std::env::remove_var("XDG_CONFIG_HOME");
+        (dir, config.unwrap())
+    }
+
+    fn mock_client(responses: &[&str]) -> (TempDir, Arc<MockProvider>, AIClient) {
+        let provider = Arc::new(MockProvider::new(
+            responses.iter().map(|r| r.to_string()).collect(),
+        ));
+        let (config_dir, config) = temp_config();
+        let client =
+            AIClient::with_provider(Box::new(provider.clone()), "mock".to_string(), config)
+                .unwrap();
+        (config_dir, provider, client)
+    }
+
+    #[tokio::test]
+    async fn test_generate_formatted_commit_message_with_mock_provider() {
+        let repo = setup_staged_repo();
+        let git_client = GitClient::with_working_dir(repo.path().to_string_lossy().to_string());
+        let (_config_dir, provider, ai_client) =
+            mock_client(&["<commit_message>\nfeat(hello): Add greeting file\n</commit_message>"]);
+
+        let messages =
+            generate_formatted_commit_messages(&git_client, &ai_client, &settings(1), &[])
+                .await
+                .unwrap();
+
+        assert_eq!(messages, ["feat(hello): Add greeting file"]);
+        let requests = provider.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].messages[1].content.contains("+hello world"));
+    }
+
+    #[tokio::test]
+    async fn test_generate_formatted_commit_messages_deduplicates_candidates() {
+        let repo = setup_staged_repo();
+        let git_client = GitClient::with_working_dir(repo.path().to_string_lossy().to_string());
+        let (_config_dir, _provider, ai_client) = mock_client(&[
+            "feat(hello): Add greeting file",
+            "<commit_message>feat(hello): Add greeting file</commit_message>",
+            "docs(hello): Document greeting",
+        ]);
+
+        let messages =
+            generate_formatted_commit_messages(&git_client, &ai_client, &settings(3), &[])
+                .await
+                .unwrap();
+
+        assert_eq!(
+            messages,
+            [
+                "feat(hello): Add greeting file",
+                "docs(hello): Document greeting"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_message_is_repaired_then_reprompted() {
+        let repo = setup_staged_repo();
+        let git_client = GitClient::with_working_dir(repo.path().to_string_lossy().to_string());
+        let (_config_dir, provider, ai_client) = mock_client(&[
+            "Subject: Added hello file.",
+            "Added the hello file",
+            "chore: add hello file",
+        ]);
+
+        let messages =
+            generate_formatted_commit_messages(&git_client, &ai_client, &settings(1), &[])
+                .await
+                .unwrap();
+
+        assert_eq!(messages[0], "chore: add hello file");
+        let requests = provider.requests();
+        assert_eq!(requests.len(), 3);
+        let feedback = &requests[1].messages.last().unwrap().content;
+        assert!(feedback.contains("does not match 'type(scope): subject'"));
+    }
+
+    #[tokio::test]
+    async fn test_repair_retries_are_limited() {
+        let repo = setup_staged_repo();
+        let git_client = GitClient::with_working_dir(repo.path().to_string_lossy().to_string());
+        let (_config_dir, provider, ai_client) = mock_client(&["Added hello file"]);
+        let settings = GenerationSettings {
+            repair_retries: 1,
+            ..settings(1)
+        };
+
+        let messages = generate_formatted_commit_messages(&git_client, &ai_client, &settings, &[])
+            .await
+            .unwrap();
+
+        assert_eq!(messages, ["Added hello file"]);
+        assert_eq!(provider.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_hook_writes_generated_message() {
+        let repo = setup_staged_repo();
+        let git_client = GitClient::with_working_dir(repo.path().to_string_lossy().to_string());
+        let (_config_dir, _provider, ai_client) = mock_client(&[
+            "```shell\nchore: Add hello file\n```",
+            "docs: Describe hello file",
+            "chore: add hello file",
+        ]);
+        let msg_file = repo.path().join("COMMIT_EDITMSG");
+
+        write_hook_commit_message(
+            &git_client,
+            &ai_client,
+            &settings(3),
+            msg_file.to_str().unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(msg_file).unwrap(),
+            "chore: Add hello file"
+        );
+    }
+
+    #[test]
+    fn test_hook_failure_policies() {
+        let repo = setup_staged_repo();
+        let git_client = GitClient::with_working_dir(repo.path().to_string_lossy().to_string());
+        let msg_file = repo.path().join("COMMIT_EDITMSG");
+        let path = msg_file.to_str().unwrap();
+        let git_template = "\n# Please enter the commit message for your changes.\n";
+        let recover = |policy| {
+            recover_hook_failure(
+                policy,
+                &git_client,
+                &LintRules::default(),
+                path,
+                "OpenAI API error: 503 Service Unavailable".into(),
+            )
+        };
+
+        fs::write(&msg_file, git_template).unwrap();
+        assert!(recover(HookFailure::Block).is_err());
+        assert_eq!(fs::read_to_string(&msg_file).unwrap(), git_template);
+
+        recover(HookFailure::Warn).unwrap();
+        let contents = fs::read_to_string(&msg_file).unwrap();
+        assert!(contents.starts_with(
+            "# iamcommitted could not generate a message:\n#   OpenAI API error: 503"
+        ));
+        assert!(contents.ends_with(git_template));
+        assert_eq!(strip_comments(&contents), "");
+
+        fs::write(&msg_file, git_template).unwrap();
+        recover(HookFailure::Heuristic).unwrap();
+        let contents = fs::read_to_string(&msg_file).unwrap();
+        assert_eq!(strip_comments(&contents), "chore: update hello.txt");
+        assert!(contents.ends_with(git_template));
+
+        // An amended commit keeps its message
+        fs::write(&msg_file, "fix: keep this\n").unwrap();
+        recover(HookFailure::Heuristic).unwrap();
+        let contents = fs::read_to_string(&msg_file).unwrap();
+        assert_eq!(strip_comments(&contents), "fix: keep this");
+    }
+
+    #[test]
+    fn test_hook_survives_invalid_config() {
+        let repo = setup_staged_repo();
+        let git_client = GitClient::with_working_dir(repo.path().to_string_lossy().to_string());
+        let msg_file = repo.path().join("COMMIT_EDITMSG");
+        fs::write(
+            &msg_file,
+            "\n# Please enter the commit message for your changes.\n",
+        )
+        .unwrap();
+
+        let error = {
+            let _env = ENV_LOCK
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let dir = TempDir::new().unwrap();
+            fs::create_dir_all(dir.path().join("iamcommitted")).unwrap();
+            fs::write(
+                dir.path().join("iamcommitted/config.toml"),
+                "candidates = \"three\"\n",
+            )
+            .unwrap();
+            std::env::set_var("XDG_CONFIG_HOME", dir.path());
+            let loaded = Config::new_lenient();
+            std::env::remove_var("XDG_CONFIG_HOME");
+            loaded.err().unwrap()
+        };
+
+        recover_hook_config_error(&git_client, msg_file.to_str().unwrap(), error).unwrap();
+        let contents = fs::read_to_string(&msg_file).unwrap();
+        assert_eq!(strip_comments(&contents), "chore: update hello.txt");
+        assert!(contents.contains("#   Could not load the configuration: "));
+        assert!(keeps_own_message(Some("message")));
+        assert!(!keeps_own_message(Some("commit")));
+    }
+
+    #[test]
+    fn test_lint_args_override_default_rules() {
+        let cli = Cli::parse_from([
+            "iamcommitted",
+            "commit-msg",
+            ".git/COMMIT_EDITMSG",
+            "--types",
+            "feat,fix",
+            "--require-scope",
+            "--body-width",
+            "100",
+        ]);
+
+        match cli.command {
+            Some(Commands::CommitMsg { rules, .. }) => {
+                let rules = rules.rules(LintRules::default());
+                assert_eq!(rules.types, ["feat", "fix"]);
+                assert!(rules.require_scope);
+                assert_eq!(rules.body_wrap_width, 100);
+                assert_eq!(
+                    rules.max_subject_length,
+                    LintRules::default().max_subject_length
+                );
+            }
+            _ => panic!("expected the commit-msg subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_global_flags_become_overrides() {
+        let cli = Cli::parse_from([
+            "iamcommitted",
+            "models",
+            "--provider",
+            "ollama",
+            "--candidates",
+            "2",
+            "--profile",
+            "local",
+        ]);
+        let overrides = cli.overrides();
+        assert_eq!(overrides.profile.as_deref(), Some("local"));
+
+        assert_eq!(overrides.provider.as_deref(), Some("ollama"));
+        assert_eq!(overrides.candidates, Some(2));
+        assert_eq!(overrides.model, None);
+    }
+
+    #[test]
+    fn test_config_set_args() {
+        let cli = Cli::parse_from([
+            "iamcommitted",
+            "config",
+            "set",
+            "--repo",
+            "format.scopes",
+            "api,ui",
+        ]);
+
+        match cli.command {
+            Some(Commands::Config {
+                action: ConfigAction::Set { key, value, repo },
+            }) => {
+                assert_eq!(key, "format.scopes");
+                assert_eq!(value, "api,ui");
+                assert!(repo);
+            }
+            _ => panic!("expected config set"),
+        }
+    }
 
     #[test]
     fn test_help_contains_environment_variables() {